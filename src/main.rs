@@ -2,7 +2,21 @@ mod pong;
 
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
+use pong::actions::ActionsPlugin;
 use pong::assets::LoadAssetsPlugin;
+use pong::controls::ControlsPlugin;
+use pong::powerups::PowerUpsPlugin;
+use pong::obstacles::ObstaclesPlugin;
+use pong::level::LevelPlugin;
+use pong::editor::EditorPlugin;
+use pong::topology::TopologyPlugin;
+use pong::squash::SquashPlugin;
+use pong::breakout::BreakoutPlugin;
+use pong::campaign::CampaignPlugin;
+use pong::spacetime::SpacetimePlugin;
+use pong::relativity::RelativityPlugin;
+use pong::trajectory::TrajectoryPlugin;
+use pong::colormap::ColorMapPlugin;
 use pong::ui::UIPlugin;
 
 use crate::pong::pong::PongPlugin;
@@ -12,8 +26,22 @@ fn main() {
     App::new()    
         .add_plugins(DefaultPlugins)
         .add_loopless_state(PongState::LoadingAssets)
+        .add_plugin(ActionsPlugin)
         .add_plugin(LoadAssetsPlugin)
         .add_plugin(UIPlugin)
         .add_plugin(PongPlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(PowerUpsPlugin)
+        .add_plugin(ObstaclesPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(EditorPlugin)
+        .add_plugin(TopologyPlugin)
+        .add_plugin(SquashPlugin)
+        .add_plugin(BreakoutPlugin)
+        .add_plugin(CampaignPlugin)
+        .add_plugin(SpacetimePlugin)
+        .add_plugin(RelativityPlugin)
+        .add_plugin(TrajectoryPlugin)
+        .add_plugin(ColorMapPlugin)
         .run();
 }
\ No newline at end of file
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::config::{get_user_config_path, load_ron};
+use crate::pong::player::Player;
+use crate::pong::resources::GamepadAssignments;
+
+// The defaults that ship with the game.  Rebinding saves to the player's own copy instead.
+pub const BINDINGS_PATH: &str = "config/bindings.ron";
+pub const USER_BINDINGS_FILE: &str = "bindings.ron";
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        let user_bindings_path = get_user_bindings_path();
+        let loaded = if user_bindings_path.exists() {
+            load_ron(&user_bindings_path)
+        } else {
+            load_ron(BINDINGS_PATH)
+        };
+        let key_bindings = loaded.unwrap_or_else(|error| {
+            println!("Failed to load key bindings, falling back to the defaults: {}", error);
+            KeyBindings::default()
+        });
+        app
+            .insert_resource(key_bindings)
+            .insert_resource(GamepadAssignments::default())
+            .insert_resource(Input::<PlayerAction>::default())
+            .add_system_to_stage(CoreStage::PreUpdate, action_input_system.after(InputSystem));
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveXPos,
+    MoveXNeg,
+    MoveYPos,
+    MoveYNeg,
+    MoveZPos,
+    MoveZNeg,
+    MoveWPos,
+    MoveWNeg,
+    Serve,
+    Pause,
+}
+
+impl Action {
+
+    pub const ALL: [Action; 10] = [
+        Action::MoveXPos,
+        Action::MoveXNeg,
+        Action::MoveYPos,
+        Action::MoveYNeg,
+        Action::MoveZPos,
+        Action::MoveZNeg,
+        Action::MoveWPos,
+        Action::MoveWNeg,
+        Action::Serve,
+        Action::Pause,
+    ];
+
+    pub fn gamepad_button(&self) -> GamepadButtonType {
+        match self {
+            Action::MoveXPos => GamepadButtonType::DPadRight,
+            Action::MoveXNeg => GamepadButtonType::DPadLeft,
+            Action::MoveYPos => GamepadButtonType::DPadUp,
+            Action::MoveYNeg => GamepadButtonType::DPadDown,
+            Action::MoveZPos => GamepadButtonType::North,
+            Action::MoveZNeg => GamepadButtonType::South,
+            Action::MoveWPos => GamepadButtonType::RightTrigger,
+            Action::MoveWNeg => GamepadButtonType::LeftTrigger,
+            Action::Serve => GamepadButtonType::West,
+            Action::Pause => GamepadButtonType::Start,
+        }
+    }
+
+    pub fn direction(&self) -> Option<Vec4> {
+        match self {
+            Action::MoveXPos => Some(Vec4::X),
+            Action::MoveXNeg => Some(-Vec4::X),
+            Action::MoveYPos => Some(Vec4::Y),
+            Action::MoveYNeg => Some(-Vec4::Y),
+            Action::MoveZPos => Some(Vec4::Z),
+            Action::MoveZNeg => Some(-Vec4::Z),
+            Action::MoveWPos => Some(Vec4::W),
+            Action::MoveWNeg => Some(-Vec4::W),
+            Action::Serve => None,
+            Action::Pause => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PlayerAction(pub Player, pub Action);
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::MoveXPos => write!(f, "Move +X"),
+            Action::MoveXNeg => write!(f, "Move -X"),
+            Action::MoveYPos => write!(f, "Move +Y"),
+            Action::MoveYNeg => write!(f, "Move -Y"),
+            Action::MoveZPos => write!(f, "Move +Z"),
+            Action::MoveZNeg => write!(f, "Move -Z"),
+            Action::MoveWPos => write!(f, "Move +W"),
+            Action::MoveWNeg => write!(f, "Move -W"),
+            Action::Serve => write!(f, "Serve"),
+            Action::Pause => write!(f, "Pause"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings(BTreeMap<Player, BTreeMap<Action, KeyCode>>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(
+            BTreeMap::from([
+                (
+                    Player::Blue,
+                    BTreeMap::from([
+                        (Action::MoveXPos, KeyCode::D),
+                        (Action::MoveXNeg, KeyCode::A),
+                        (Action::MoveYPos, KeyCode::Up),
+                        (Action::MoveYNeg, KeyCode::Down),
+                        (Action::MoveZPos, KeyCode::W),
+                        (Action::MoveZNeg, KeyCode::S),
+                        (Action::MoveWPos, KeyCode::E),
+                        (Action::MoveWNeg, KeyCode::Q),
+                        (Action::Serve, KeyCode::Space),
+                        (Action::Pause, KeyCode::Escape),
+                    ])
+                ),
+                (
+                    Player::Red,
+                    BTreeMap::from([
+                        (Action::MoveXPos, KeyCode::L),
+                        (Action::MoveXNeg, KeyCode::J),
+                        (Action::MoveYPos, KeyCode::O),
+                        (Action::MoveYNeg, KeyCode::U),
+                        (Action::MoveZPos, KeyCode::I),
+                        (Action::MoveZNeg, KeyCode::K),
+                        (Action::MoveWPos, KeyCode::Y),
+                        (Action::MoveWNeg, KeyCode::H),
+                        (Action::Serve, KeyCode::Return),
+                        (Action::Pause, KeyCode::P),
+                    ])
+                ),
+            ])
+        )
+    }
+}
+
+impl KeyBindings {
+
+    pub fn get(&self, player: Player, action: Action) -> Option<KeyCode> {
+        self.0.get(&player).and_then(|bindings| bindings.get(&action)).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PlayerAction, KeyCode)> + '_ {
+        self.0.iter().flat_map(|(player, bindings)| {
+            bindings.iter().map(move |(action, key)| (PlayerAction(*player, *action), *key))
+        })
+    }
+
+    pub fn find_conflict(&self, player_action: PlayerAction, key: KeyCode) -> Option<PlayerAction> {
+        self.iter()
+            .find(|(other_action, other_key)| *other_action != player_action && *other_key == key)
+            .map(|(other_action, _)| other_action)
+    }
+
+    // Returns the action that already uses the key if there is one, leaving the bindings untouched.
+    // Keys are unique across both players so that hot-seat players can't steal each other's controls.
+    pub fn rebind(&mut self, player_action: PlayerAction, key: KeyCode) -> Result<(), PlayerAction> {
+        match self.find_conflict(player_action, key) {
+            Some(conflicting_action) => Err(conflicting_action),
+            None => {
+                let PlayerAction(player, action) = player_action;
+                self.0.entry(player).or_default().insert(action, key);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn describe(&self, player: Player) -> String {
+        Action::ALL.iter()
+            .map(|action| {
+                let key = self.get(player, *action).map_or("-".to_string(), |key| format!("{:?}", key));
+                format!("{}: {}", action, key)
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+// Systems
+
+fn action_input_system(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepad_assignments: Res<GamepadAssignments>,
+    key_bindings: Res<KeyBindings>,
+    mut window_focused_events: EventReader<WindowFocused>,
+    mut action_input: ResMut<Input<PlayerAction>>,
+) {
+    // Key releases that happen while the window is unfocused never reach us, so forget every held key.
+    if window_focused_events.iter().any(|event| !event.focused) {
+        let pressed_keys: Vec<KeyCode> = keyboard_input.get_pressed().copied().collect();
+        for key in pressed_keys {
+            keyboard_input.release(key);
+        }
+    }
+
+    action_input.clear();
+    for (player_action, key) in key_bindings.iter() {
+        let PlayerAction(player, action) = player_action;
+        let is_gamepad_button_pressed = gamepad_assignments.get(player)
+            .map_or(false, |gamepad| gamepad_input.pressed(GamepadButton(gamepad, action.gamepad_button())));
+        if keyboard_input.pressed(key) || is_gamepad_button_pressed {
+            action_input.press(player_action);
+        } else {
+            action_input.release(player_action);
+        }
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+pub fn get_user_bindings_path() -> PathBuf {
+    get_user_config_path(USER_BINDINGS_FILE)
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_actions {
+    use super::*;
+    use crate::pong::config::save_ron;
+
+    #[test]
+    fn test_rebind_detects_conflicts() {
+        let mut key_bindings = KeyBindings::default();
+        let blue_move_x = PlayerAction(Player::Blue, Action::MoveXPos);
+        assert_eq!(key_bindings.rebind(blue_move_x, KeyCode::W), Err(PlayerAction(Player::Blue, Action::MoveZPos)));
+        assert_eq!(key_bindings.rebind(blue_move_x, KeyCode::I), Err(PlayerAction(Player::Red, Action::MoveZPos)));
+        assert_eq!(key_bindings.get(Player::Blue, Action::MoveXPos), Some(KeyCode::D));
+
+        assert_eq!(key_bindings.rebind(blue_move_x, KeyCode::D), Ok(()));
+        assert_eq!(key_bindings.rebind(blue_move_x, KeyCode::F), Ok(()));
+        assert_eq!(key_bindings.get(Player::Blue, Action::MoveXPos), Some(KeyCode::F));
+        assert_eq!(key_bindings.find_conflict(PlayerAction(Player::Red, Action::MoveXNeg), KeyCode::F), Some(blue_move_x));
+    }
+
+    #[test]
+    fn test_bindings_round_trip() {
+        let key_bindings = KeyBindings::default();
+        let contents = ron::ser::to_string_pretty(&key_bindings, ron::ser::PrettyConfig::new()).unwrap();
+        assert_eq!(ron::from_str::<KeyBindings>(&contents).unwrap(), key_bindings);
+    }
+
+    #[test]
+    fn test_bindings_save_and_load() {
+        let path = std::env::temp_dir().join("four-dimensional-pong-test").join(USER_BINDINGS_FILE);
+        let mut key_bindings = KeyBindings::default();
+        key_bindings.rebind(PlayerAction(Player::Blue, Action::MoveXPos), KeyCode::F).unwrap();
+        save_ron(&key_bindings, &path).unwrap();
+        assert_eq!(load_ron::<KeyBindings>(&path).unwrap(), key_bindings);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bindings_file_matches_defaults() {
+        assert_eq!(load_ron::<KeyBindings>(BINDINGS_PATH).unwrap(), KeyBindings::default());
+    }
+
+    #[test]
+    fn test_every_action_is_bound_by_default() {
+        let key_bindings = KeyBindings::default();
+        for player in Player::HEAD_TO_HEAD {
+            for action in Action::ALL {
+                assert!(key_bindings.get(player, action).is_some(), "{} is not bound for {}", action, player);
+            }
+        }
+        for (player_action, key) in key_bindings.iter() {
+            assert_eq!(key_bindings.find_conflict(player_action, key), None);
+        }
+    }
+}
@@ -99,6 +99,7 @@ fn stage_load_system(
     assets_gltf: Res<Assets<Gltf>>,
     assets_gltf_meshes: Res<Assets<GltfMesh>>,
     projection_images: Res<ProjectionImages>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     if asset_server.get_load_state(&model.0) == LoadState::Failed {
         println!("Failed to load gltf.");
@@ -107,9 +108,9 @@ fn stage_load_system(
     if let Some(model_root) = assets_gltf.get(&model.0) {
         let arena = model_root.named_meshes["Arena"].clone();
         let rectangular_arena = model_root.named_meshes["Rectangular Arena"].clone();
-        let ball = model_root.named_meshes["Ball"].clone();
-        let player_paddle = model_root.named_meshes["Blue Paddle"].clone();
-        let opponent_paddle = model_root.named_meshes["Red Paddle"].clone();
+        let ball = get_mesh_from_gltf_or_panic(&assets_gltf_meshes, &model_root.named_meshes["Ball"]);
+        let player_paddle = get_mesh_from_gltf_or_panic(&assets_gltf_meshes, &model_root.named_meshes["Blue Paddle"]);
+        let opponent_paddle = get_mesh_from_gltf_or_panic(&assets_gltf_meshes, &model_root.named_meshes["Red Paddle"]);
 
         let arena_material = model_root.named_materials["Arena Material"].clone();
         let ball_material = model_root.named_materials["Ball Material"].clone();
@@ -122,7 +123,7 @@ fn stage_load_system(
                 material: arena_material.clone(),
                 ..Default::default()
             }
-        );
+        ).insert(ArenaComponent);
 
         let arena_rect_transform = Transform::from_xyz(0.0, Y_OFFSET_FOR_PROJECTIONS, 0.0);
         spawn_arena_rectangle(&mut commands, &assets_gltf_meshes, &rectangular_arena, &arena_material, &(Transform::from_xyz(-DELTA_X_FOR_PROJECTIONS, 0.0, 0.0) * arena_rect_transform));
@@ -131,7 +132,6 @@ fn stage_load_system(
 
         spawn_object_and_projections(
             &mut commands,
-            &assets_gltf_meshes,
             &ball,
             &ball_material,
             Vec4::ZERO,
@@ -140,38 +140,40 @@ fn stage_load_system(
             None::<AIComponent>,
         );
         
-        let player_starting_position = Vec4::new(
-            0.,
-            0.,
-            -PADDLE_STARTING_OFFSET,
-            -ARENA_LENGTH / 2.
-        );
-        let opponent_starting_position = Vec4::new(
-            0.,
-            0.,
-            PADDLE_STARTING_OFFSET,
-            ARENA_LENGTH / 2.0
+        // Shows which way the ball will go when it's served.
+        let serve_marker_material = add_w_coloured_material(&mut materials, AlphaMode::Opaque);
+        spawn_object_and_projections(
+            &mut commands,
+            &ball,
+            &serve_marker_material,
+            Vec4::ZERO,
+            Some(SERVE_MARKER_SCALE),
+            ServeMarkerComponent,
+            None::<AIComponent>,
         );
 
+        // The level's arena gets applied when the match starts.
+        let default_arena = Arena::default();
+        let player_starting_position = default_arena.get_paddle_starting_position(Player::Blue);
+        let opponent_starting_position = default_arena.get_paddle_starting_position(Player::Red);
+
         spawn_object_and_projections(
             &mut commands,
-            &assets_gltf_meshes,
             &player_paddle,
             &player_paddle_material,
             player_starting_position,
-            Some(PADDLE_SIZE_MEDIUM),
+            Some(default_arena.paddle_size),
             PaddleComponent(Player::Blue),
-            Some(PlayerInputComponent),
+            Some(PlayerInputComponent(ControlScheme::Keyboard)),
         );
 
 
         spawn_object_and_projections(
             &mut commands,
-            &assets_gltf_meshes,
             &opponent_paddle,
             &opponent_paddle_material,
             opponent_starting_position,
-            Some(PADDLE_SIZE_MEDIUM),
+            Some(default_arena.paddle_size),
             PaddleComponent(Player::Red),
             Some(AIComponent(AI_PADDLE_SPEED_MEDIUM)),
         );
@@ -187,7 +189,7 @@ fn stage_load_system(
                 transform: transform,
                 ..default()
             }
-        );
+        ).insert(MainCameraComponent);
 
         let (xw_image_handle, yw_image_handle, zw_image_handle) = projection_images.unpack();
 
@@ -218,23 +220,21 @@ fn spawn_arena_rectangle(
             transform: transform.clone(),
             ..Default::default()
         }
-    );
+    ).insert(ArenaRectangleComponent);
 }
 
-fn spawn_object_and_projections(
+pub fn spawn_object_and_projections(
     commands: &mut Commands,
-    assets_gltf_meshes: &Res<Assets<GltfMesh>>,
-    mesh: &Handle<GltfMesh>,
+    mesh: &Handle<Mesh>,
     material: &Handle<StandardMaterial>,
     position: Vec4,
     scale_factor: Option<f32>,
     label_component: impl Component + Copy,
     input_or_ai_component: Option<impl Component + Copy>,
-) {
+) -> Entity {
     // Spawn actual object for the main camera
     let entity = spawn_object(
         commands,
-        assets_gltf_meshes,
         mesh,
         material,
         position,
@@ -245,7 +245,6 @@ fn spawn_object_and_projections(
 
     spawn_projection(
         commands,
-        assets_gltf_meshes,
         mesh,
         material,
         entity,
@@ -264,7 +263,6 @@ fn spawn_object_and_projections(
 
     spawn_projection(
         commands,
-        assets_gltf_meshes,
         mesh,
         material,
         entity,
@@ -282,7 +280,6 @@ fn spawn_object_and_projections(
 
     spawn_projection(
         commands,
-        assets_gltf_meshes,
         mesh,
         material,
         entity,
@@ -298,12 +295,30 @@ fn spawn_object_and_projections(
         ),
         scale_factor,
     );
+
+    entity
+}
+
+// render_system sets each object's colour from its own position, so anything it colours can't share a material.
+pub fn add_w_coloured_material(materials: &mut Assets<StandardMaterial>, alpha_mode: AlphaMode) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        alpha_mode,
+        ..StandardMaterial::from(Color::WHITE)
+    })
+}
+
+pub fn despawn_object_and_projections(commands: &mut Commands, entity: Entity, projection_query: &Query<(Entity, &ProjectionComponent)>) {
+    commands.entity(entity).despawn();
+    for (projection_entity, projection_component) in projection_query.iter() {
+        if projection_component.0 == entity {
+            commands.entity(projection_entity).despawn();
+        }
+    }
 }
 
 fn spawn_object(
     commands: &mut Commands,
-    assets_gltf_meshes: &Res<Assets<GltfMesh>>,
-    mesh: &Handle<GltfMesh>,
+    mesh: &Handle<Mesh>,
     material: &Handle<StandardMaterial>,
     position: Vec4,
     scale_factor: Option<f32>,
@@ -314,7 +329,7 @@ fn spawn_object(
     let mut entity_commands = commands.spawn_bundle(
         PbrBundle {
             transform: transform,
-            mesh: get_mesh_from_gltf_or_panic(&assets_gltf_meshes, &mesh),
+            mesh: mesh.clone(),
             material: material.clone(),
             ..Default::default()
         }
@@ -346,8 +361,7 @@ fn spawn_object(
 
 fn spawn_projection(
     commands: &mut Commands,
-    assets_gltf_meshes: &Res<Assets<GltfMesh>>,
-    mesh: &Handle<GltfMesh>,
+    mesh: &Handle<Mesh>,
     material: &Handle<StandardMaterial>,
     object_id: Entity,
     projection_rotations: Vec<Rotation>,
@@ -358,7 +372,7 @@ fn spawn_projection(
     let mut entity_commands = commands.spawn_bundle(
         PbrBundle {
             transform: projection_transform,
-            mesh: get_mesh_from_gltf_or_panic(&assets_gltf_meshes, &mesh),
+            mesh: mesh.clone(),
             material: material.clone(),
             ..Default::default()
         }
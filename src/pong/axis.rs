@@ -1,9 +1,21 @@
-
-
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
     Z,
     W,
-}
\ No newline at end of file
+}
+
+impl Axis {
+
+    pub const TRANSVERSE: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    pub fn index(&self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+            Axis::W => 3,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::assets::{add_w_coloured_material, despawn_object_and_projections, spawn_object_and_projections};
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::obstacles::{get_obstacle_contact, reflect_off_surface, ObstacleShape};
+use crate::pong::pong::ScoreEvent;
+use crate::pong::resources::*;
+
+pub struct BreakoutPlugin;
+
+impl Plugin for BreakoutPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(BrickGrid::default())
+            .insert_resource(BrickField::default())
+            .insert_resource(Lives(BREAKOUT_LIVES))
+            .add_startup_system(brick_mesh_load_system)
+            .add_exit_system(PongState::Menu, spawn_bricks_system)
+            .add_exit_system(PongState::GameOver, spawn_bricks_system)
+            // Test runs go straight from the editor into a match.
+            .add_exit_system(PongState::Editor, spawn_bricks_system)
+            .add_system(brick_grid_sync_system.run_in_state(PongState::InGame).run_if(is_breakout))
+            .add_system(brick_collision_system.run_in_state(PongState::InGame).run_if(is_breakout))
+            .add_system(breakout_miss_system.run_in_state(PongState::InGame).run_if(is_breakout));
+    }
+}
+
+// A cell in the brick grid, counted along x, y, z and w.
+pub type BrickCell = [usize; 4];
+
+#[derive(Component, Clone, Copy)]
+pub struct BrickComponent {
+    pub cell: BrickCell,
+    pub half_extents: Vec4,
+}
+
+// Resources
+
+// The bricks fill the arena across x, y and z, and the given stretch of w at the far end.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrickGrid {
+    pub cells: BrickCell,
+    pub w_range: Vec2,
+    pub gap: f32,
+    // Cells to leave empty, for layouts that aren't a solid block.
+    pub holes: Vec<BrickCell>,
+}
+
+impl Default for BrickGrid {
+    fn default() -> Self {
+        Self {
+            cells: [3, 3, 3, 2],
+            w_range: Vec2::new(1.0, 2.0),
+            gap: 0.05,
+            holes: Vec::new(),
+        }
+    }
+}
+
+impl BrickGrid {
+
+    fn get_origin(&self, arena: &Arena) -> Vec4 {
+        Vec3::splat(-arena.width / 2.).extend(self.w_range.x)
+    }
+
+    pub fn get_cell_size(&self, arena: &Arena) -> Vec4 {
+        let [x, y, z, w] = self.cells.map(|count| count.max(1) as f32);
+        Vec4::new(arena.width / x, arena.width / y, arena.width / z, (self.w_range.y - self.w_range.x) / w)
+    }
+
+    pub fn get_half_extents(&self, arena: &Arena) -> Vec4 {
+        (self.get_cell_size(arena) - Vec4::splat(self.gap)) / 2.
+    }
+
+    pub fn get_center(&self, cell: BrickCell, arena: &Arena) -> Vec4 {
+        let cell_position = Vec4::from(cell.map(|index| index as f32)) + Vec4::splat(0.5);
+        self.get_origin(arena) + cell_position * self.get_cell_size(arena)
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.cells.iter().product()
+    }
+
+    pub fn get_index(&self, cell: BrickCell) -> usize {
+        let [_, y, z, w] = self.cells;
+        ((cell[0] * y + cell[1]) * z + cell[2]) * w + cell[3]
+    }
+
+    // Every cell that holds a brick at the start.
+    pub fn get_brick_cells(&self) -> Vec<BrickCell> {
+        let [x, y, z, w] = self.cells;
+        let mut cells = Vec::new();
+        for i in 0..x {
+            for j in 0..y {
+                for k in 0..z {
+                    for l in 0..w {
+                        cells.push([i, j, k, l]);
+                    }
+                }
+            }
+        }
+        cells.retain(|cell| !self.holes.contains(cell));
+        cells
+    }
+
+    // The cells a ball of the given radius could be touching, so only those few bricks need checking.
+    pub fn get_cells_near(&self, position: Vec4, radius: f32, arena: &Arena) -> Vec<BrickCell> {
+        let origin = self.get_origin(arena);
+        let cell_size = self.get_cell_size(arena);
+        let mut ranges = [(0, 0); 4];
+        for axis in 0..4 {
+            let count = self.cells[axis];
+            let low = ((position[axis] - radius - origin[axis]) / cell_size[axis]).floor();
+            let high = ((position[axis] + radius - origin[axis]) / cell_size[axis]).floor();
+            if count == 0 || high < 0. || low >= count as f32 {
+                return Vec::new();
+            }
+            ranges[axis] = (low.max(0.) as usize, (high as usize).min(count - 1));
+        }
+        let mut cells = Vec::new();
+        for i in ranges[0].0..=ranges[0].1 {
+            for j in ranges[1].0..=ranges[1].1 {
+                for k in ranges[2].0..=ranges[2].1 {
+                    for l in ranges[3].0..=ranges[3].1 {
+                        cells.push([i, j, k, l]);
+                    }
+                }
+            }
+        }
+        cells
+    }
+}
+
+// Which brick, if any, is in each cell of the grid, so that collisions are a lookup rather than a search.
+#[derive(Debug, Clone, Default)]
+pub struct BrickField {
+    pub bricks: Vec<Option<Entity>>,
+    pub remaining: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lives(pub usize);
+
+struct BrickMesh(Handle<Mesh>);
+
+// End Resources
+
+// Run Conditions
+
+fn is_breakout(game_mode: Res<GameMode>) -> bool {
+    *game_mode == GameMode::Breakout
+}
+
+// End Run Conditions
+
+// Systems
+
+fn brick_mesh_load_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    // Unit-sized so that render_system can stretch it to the bricks' extents.
+    commands.insert_resource(BrickMesh(meshes.add(Mesh::from(shape::Cube { size: 2. }))));
+}
+
+fn spawn_bricks_system(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    arena: Res<Arena>,
+    brick_grid: Res<BrickGrid>,
+    brick_mesh: Res<BrickMesh>,
+    mut brick_field: ResMut<BrickField>,
+    mut lives: ResMut<Lives>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    brick_query: Query<Entity, With<BrickComponent>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    *lives = Lives(BREAKOUT_LIVES);
+    let is_breakout = *game_mode == GameMode::Breakout;
+    respawn_bricks(&mut commands, is_breakout, &arena, &brick_grid, &brick_mesh, &mut brick_field, &mut materials, &brick_query, &projection_query);
+}
+
+fn brick_grid_sync_system(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    brick_grid: Res<BrickGrid>,
+    brick_mesh: Res<BrickMesh>,
+    mut brick_field: ResMut<BrickField>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    brick_query: Query<Entity, With<BrickComponent>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    // The level was reloaded mid-match, so the bricks no longer line up with the grid.
+    if !brick_grid.is_changed() {
+        return;
+    }
+    respawn_bricks(&mut commands, true, &arena, &brick_grid, &brick_mesh, &mut brick_field, &mut materials, &brick_query, &projection_query);
+}
+
+fn brick_collision_system(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    serve: Res<Serve>,
+    brick_grid: Res<BrickGrid>,
+    mut brick_field: ResMut<BrickField>,
+    mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent), With<BallComponent>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    if !serve.is_in_play() {
+        return;
+    }
+    let half_extents = brick_grid.get_half_extents(&arena);
+    for (mut ball_position, mut ball_velocity) in ball_query.iter_mut() {
+        for cell in brick_grid.get_cells_near(ball_position.0, BALL_RADIUS, &arena) {
+            let index = brick_grid.get_index(cell);
+            let entity = match brick_field.bricks.get(index).copied().flatten() {
+                Some(entity) => entity,
+                None => continue,
+            };
+            let center = brick_grid.get_center(cell, &arena);
+            match get_obstacle_contact(ObstacleShape::Box { half_extents }, center, ball_position.0, BALL_RADIUS) {
+                Some((normal, penetration)) => {
+                    ball_position.0 += normal * penetration;
+                    ball_velocity.0 = reflect_off_surface(ball_velocity.0, normal);
+                    despawn_object_and_projections(&mut commands, entity, &projection_query);
+                    brick_field.bricks[index] = None;
+                    brick_field.remaining -= 1;
+                },
+                None => {
+                    // Do nothing
+                }
+            }
+        }
+    }
+    if brick_field.remaining == 0 {
+        commands.insert_resource(NextState(PongState::GameOver));
+    }
+}
+
+fn breakout_miss_system(
+    mut commands: Commands,
+    mut lives: ResMut<Lives>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+) {
+    for _ in score_event_reader.iter() {
+        lives.0 = lives.0.saturating_sub(1);
+    }
+    if lives.0 == 0 {
+        commands.insert_resource(NextState(PongState::GameOver));
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+fn respawn_bricks(
+    commands: &mut Commands,
+    is_breakout: bool,
+    arena: &Arena,
+    brick_grid: &BrickGrid,
+    brick_mesh: &BrickMesh,
+    brick_field: &mut BrickField,
+    materials: &mut Assets<StandardMaterial>,
+    brick_query: &Query<Entity, With<BrickComponent>>,
+    projection_query: &Query<(Entity, &ProjectionComponent)>,
+) {
+    for entity in brick_query.iter() {
+        despawn_object_and_projections(commands, entity, projection_query);
+    }
+    *brick_field = BrickField::default();
+    if !is_breakout {
+        return;
+    }
+
+    brick_field.bricks = vec![None; brick_grid.get_count()];
+    let half_extents = brick_grid.get_half_extents(arena);
+    for cell in brick_grid.get_brick_cells() {
+        let material = add_w_coloured_material(materials, AlphaMode::Opaque);
+        let entity = spawn_object_and_projections(
+            commands,
+            &brick_mesh.0,
+            &material,
+            brick_grid.get_center(cell, arena),
+            None,
+            BrickComponent { cell, half_extents },
+            None::<AIComponent>,
+        );
+        brick_field.bricks[brick_grid.get_index(cell)] = Some(entity);
+        brick_field.remaining += 1;
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_breakout {
+    use super::*;
+
+    #[test]
+    fn test_brick_grid_geometry() {
+        let arena = Arena::default();
+        let grid = BrickGrid { cells: [2, 2, 2, 2], w_range: Vec2::new(1.0, 2.0), gap: 0., holes: Vec::new() };
+        assert_eq!(grid.get_cell_size(&arena), Vec4::new(1.0, 1.0, 1.0, 0.5));
+        assert_eq!(grid.get_center([0, 0, 0, 0], &arena), Vec4::new(-0.5, -0.5, -0.5, 1.25));
+        assert_eq!(grid.get_center([1, 1, 1, 1], &arena), Vec4::new(0.5, 0.5, 0.5, 1.75));
+        assert_eq!(grid.get_index([1, 1, 1, 1]), grid.get_count() - 1);
+    }
+
+    #[test]
+    fn test_holes_are_left_empty() {
+        let grid = BrickGrid { cells: [2, 1, 1, 2], holes: vec![[1, 0, 0, 0]], ..BrickGrid::default() };
+        assert_eq!(grid.get_brick_cells(), vec![[0, 0, 0, 0], [0, 0, 0, 1], [1, 0, 0, 1]]);
+    }
+
+    #[test]
+    fn test_get_cells_near() {
+        let arena = Arena::default();
+        let grid = BrickGrid { cells: [2, 2, 2, 2], w_range: Vec2::new(1.0, 2.0), gap: 0., holes: Vec::new() };
+        assert!(grid.get_cells_near(Vec4::ZERO, BALL_RADIUS, &arena).is_empty());
+        assert_eq!(grid.get_cells_near(Vec4::new(0.5, 0.5, 0.5, 1.25), BALL_RADIUS, &arena), vec![[1, 1, 1, 0]]);
+        // Right on a corner between cells, so it could be touching any of them.
+        assert_eq!(grid.get_cells_near(Vec4::new(0., 0.5, 0.5, 1.5), BALL_RADIUS, &arena).len(), 4);
+        assert_eq!(grid.get_cells_near(Vec4::new(0.5, 0.5, 0.5, 2.0 + BALL_RADIUS / 2.), BALL_RADIUS, &arena), vec![[1, 1, 1, 1]]);
+    }
+}
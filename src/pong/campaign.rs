@@ -0,0 +1,423 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::axis::Axis;
+use crate::pong::colormap::ColorMap;
+use crate::pong::components::*;
+use crate::pong::config::{get_user_config_path, load_ron, save_ron};
+use crate::pong::constants::*;
+use crate::pong::fields::ForceFields;
+use crate::pong::level::{Level, Levels, DEFAULT_LEVEL_PATH};
+use crate::pong::player::Player;
+use crate::pong::pong::{PaddleHitEvent, ScoreEvent};
+use crate::pong::relativity::Relativity;
+use crate::pong::resources::*;
+use crate::pong::rules::{BaseMatchRules, MatchRules};
+use crate::pong::topology::Topology;
+
+pub const CAMPAIGN_PROGRESS_FILE: &str = "campaign.ron";
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        let campaign_progress = load_ron(get_user_config_path(CAMPAIGN_PROGRESS_FILE)).unwrap_or_else(|error| {
+            println!("Failed to load campaign progress, starting from the beginning: {}", error);
+            CampaignProgress::default()
+        });
+        app
+            .insert_resource(campaign_progress)
+            .insert_resource(Campaign::default())
+            .insert_resource(SavedMenuSettings::default())
+            .add_enter_system(PongState::Menu, leave_campaign_system)
+            .add_exit_system(PongState::Menu, start_stage_system)
+            .add_exit_system(PongState::GameOver, start_stage_system)
+            .add_system(campaign_menu_system.run_in_state(PongState::Menu))
+            .add_system(dimension_lock_system.run_in_state(PongState::InGame).run_if(is_in_campaign))
+            .add_system(stage_progress_system.run_in_state(PongState::InGame).run_if(is_in_campaign))
+            .add_system(stage_ui_system.run_in_state(PongState::InGame).run_if(is_in_campaign));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageGoal {
+    // Hit the ball back this many times.
+    Returns(usize),
+    // Score this many points against the AI.
+    Points(usize),
+}
+
+impl Display for StageGoal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StageGoal::Returns(returns) => write!(f, "Return the ball {} times", returns),
+            StageGoal::Points(points) => write!(f, "Score {} points", points),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CampaignStage {
+    pub title: &'static str,
+    pub explanation: &'static str,
+    // How many of the axes are in play, always including w.
+    pub dimensions: usize,
+    pub goal: StageGoal,
+}
+
+impl CampaignStage {
+
+    pub fn is_axis_active(&self, axis: Axis) -> bool {
+        axis == Axis::W || axis.index() + 1 < self.dimensions
+    }
+
+    // Zeroes the axes that aren't in play yet.
+    pub fn lock(&self, vector: Vec4) -> Vec4 {
+        let mut locked = vector;
+        for axis in Axis::TRANSVERSE {
+            if !self.is_axis_active(axis) {
+                locked[axis.index()] = 0.;
+            }
+        }
+        locked
+    }
+}
+
+// The explanations follow the Answers section of the README.
+pub const CAMPAIGN: [CampaignStage; 4] = [
+    CampaignStage {
+        title: "2D: A Line and a Colour",
+        explanation: "What is a dimension?  An independent number that you need to specify in order to tell me something.  \
+            Here the ball needs two: x, across the screen, and w, along the length of the arena.  \
+            w is shown as colour, and the legend at the bottom runs from your end on the left to the far end on the right.  \
+            The X-W panel shows it the classic way: that's ordinary 2D Pong.",
+        dimensions: 2,
+        goal: StageGoal::Returns(5),
+    },
+    CampaignStage {
+        title: "3D: Adding y",
+        explanation: "How can there be more dimensions?  They're just the number of numbers you need, so specify more.  \
+            Now the ball needs y as well, and the Y-W panel shows it against w.  \
+            The ball is ready to hit when it's the same colour as your paddle.",
+        dimensions: 3,
+        goal: StageGoal::Returns(5),
+    },
+    CampaignStage {
+        title: "4D: Adding z",
+        explanation: "How can we visualize a fourth dimension?  Probably you can't, at least not in the way you'd want to.  \
+            But you can do it using colour!  x, y and z are drawn in 3D and w is still the colour, \
+            so keep your paddle under the ball and hit it as its dot on the legend reaches your paddle's mark.",
+        dimensions: 4,
+        goal: StageGoal::Returns(5),
+    },
+    CampaignStage {
+        title: "4D: A Real Match",
+        explanation: "The paddle is a 3D cube and the goal is a 3D cube too, one dimension down from the arena, just as a \
+            2D goal is a line.  Use the projections to aim into the corner the AI can't reach.",
+        dimensions: 4,
+        goal: StageGoal::Points(3),
+    },
+];
+
+// Resources
+
+// The titles of the stages that have been passed, kept between sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CampaignProgress(pub BTreeSet<String>);
+
+impl CampaignProgress {
+
+    pub fn is_passed(&self, stage: &CampaignStage) -> bool {
+        self.0.contains(stage.title)
+    }
+
+    // Each stage opens once the one before it has been passed.
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        index == 0 || CAMPAIGN.get(index - 1).map_or(false, |stage| self.is_passed(stage))
+    }
+
+    pub fn pass(&mut self, stage: &CampaignStage) {
+        self.0.insert(stage.title.to_string());
+    }
+}
+
+// The stage being played, if any, and how far through it the player is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Campaign {
+    pub stage_index: Option<usize>,
+    pub returns: usize,
+    pub points: usize,
+    pub is_passed: bool,
+}
+
+impl Campaign {
+
+    pub fn get_stage(&self) -> Option<&'static CampaignStage> {
+        self.stage_index.and_then(|index| CAMPAIGN.get(index))
+    }
+
+    pub fn get_next_stage_index(&self) -> Option<usize> {
+        self.stage_index.map(|index| index + 1).filter(|index| *index < CAMPAIGN.len())
+    }
+
+    pub fn is_goal_met(&self) -> bool {
+        match self.get_stage().map(|stage| stage.goal) {
+            Some(StageGoal::Returns(returns)) => self.returns >= returns,
+            Some(StageGoal::Points(points)) => self.points >= points,
+            None => false,
+        }
+    }
+
+    pub fn get_progress_text(&self) -> String {
+        match self.get_stage().map(|stage| stage.goal) {
+            Some(StageGoal::Returns(returns)) => format!("Returns: {} / {}", self.returns, returns),
+            Some(StageGoal::Points(points)) => format!("Points: {} / {}", self.points, points),
+            None => String::new(),
+        }
+    }
+}
+
+// What the menu was set to before a stage started, put back when the player leaves the campaign.
+#[derive(Debug, Clone)]
+pub struct MenuSettings {
+    pub game_mode: GameMode,
+    pub power_ups_enabled: PowerUpsEnabled,
+    pub obstacles_enabled: ObstaclesEnabled,
+    pub topology: Topology,
+    pub selected_level: Handle<Level>,
+    pub base_match_rules: BaseMatchRules,
+    pub view_mode: ViewMode,
+    pub color_map: ColorMap,
+    pub relativity: Relativity,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SavedMenuSettings(pub Option<MenuSettings>);
+
+// End Resources
+
+// Run Conditions
+
+fn is_in_campaign(campaign: Res<Campaign>) -> bool {
+    campaign.stage_index.is_some()
+}
+
+// End Run Conditions
+
+// Systems
+
+fn leave_campaign_system(
+    mut commands: Commands,
+    mut campaign: ResMut<Campaign>,
+    mut saved_menu_settings: ResMut<SavedMenuSettings>,
+    mut levels: ResMut<Levels>,
+) {
+    *campaign = Campaign::default();
+    match saved_menu_settings.0.take() {
+        Some(menu_settings) => {
+            // The level's fields and rules follow from the level and the base rules once they're back.
+            commands.insert_resource(menu_settings.game_mode);
+            commands.insert_resource(menu_settings.power_ups_enabled);
+            commands.insert_resource(menu_settings.obstacles_enabled);
+            commands.insert_resource(menu_settings.topology);
+            commands.insert_resource(menu_settings.base_match_rules);
+            commands.insert_resource(menu_settings.view_mode);
+            commands.insert_resource(menu_settings.color_map);
+            commands.insert_resource(menu_settings.relativity);
+            levels.selected = menu_settings.selected_level;
+        },
+        None => {
+            // Do nothing
+        }
+    }
+}
+
+fn start_stage_system(mut campaign: ResMut<Campaign>) {
+    // Fresh attempt, whether it's a retry or the next stage.
+    *campaign = Campaign {
+        stage_index: campaign.stage_index,
+        ..Campaign::default()
+    };
+}
+
+fn campaign_menu_system(
+    mut commands: Commands,
+    mut egui_ctx: ResMut<EguiContext>,
+    asset_server: Res<AssetServer>,
+    mut campaign: ResMut<Campaign>,
+    campaign_progress: Res<CampaignProgress>,
+    mut saved_menu_settings: ResMut<SavedMenuSettings>,
+    mut game_mode: ResMut<GameMode>,
+    mut power_ups_enabled: ResMut<PowerUpsEnabled>,
+    mut obstacles_enabled: ResMut<ObstaclesEnabled>,
+    mut topology: ResMut<Topology>,
+    mut levels: ResMut<Levels>,
+    mut base_match_rules: ResMut<BaseMatchRules>,
+    mut view_mode: ResMut<ViewMode>,
+    mut color_map: ResMut<ColorMap>,
+    mut relativity: ResMut<Relativity>,
+) {
+    egui::Area::new("campaign-menu")
+        .anchor(egui::Align2::LEFT_CENTER, egui::vec2(PAUSE_SCREEN_SPACING, 0.))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new("Climbing the Dimensions")
+                .size(20.)
+                .text_style(egui::TextStyle::Heading)
+                .color(egui::Color32::WHITE)
+            );
+            for (index, stage) in CAMPAIGN.iter().enumerate() {
+                let label = if campaign_progress.is_passed(stage) {
+                    egui::RichText::new(format!("{} (passed)", stage.title)).color(egui::Color32::GREEN)
+                } else {
+                    egui::RichText::new(stage.title)
+                };
+                if ui.add_enabled(campaign_progress.is_unlocked(index), egui::Button::new(label)).clicked() {
+                    saved_menu_settings.0 = Some(MenuSettings {
+                        game_mode: *game_mode,
+                        power_ups_enabled: *power_ups_enabled,
+                        obstacles_enabled: *obstacles_enabled,
+                        topology: *topology,
+                        selected_level: levels.selected.clone(),
+                        base_match_rules: *base_match_rules,
+                        view_mode: *view_mode,
+                        color_map: *color_map,
+                        relativity: *relativity,
+                    });
+                    // Each stage adds one idea, so leave out everything else.
+                    campaign.stage_index = Some(index);
+                    *game_mode = GameMode::VersusAI;
+                    power_ups_enabled.0 = false;
+                    obstacles_enabled.0 = false;
+                    *topology = Topology::default();
+                    levels.selected = asset_server.load(DEFAULT_LEVEL_PATH);
+                    *base_match_rules = BaseMatchRules::default();
+                    *view_mode = ViewMode::default();
+                    *color_map = ColorMap::default();
+                    *relativity = Relativity::default();
+                    // The level sets these too, but not until it's applied next frame.
+                    commands.insert_resource(ForceFields::default());
+                    commands.insert_resource(MatchRules::default());
+                    commands.insert_resource(NextState(PongState::InGame));
+                }
+            }
+        });
+}
+
+fn dimension_lock_system(
+    campaign: Res<Campaign>,
+    mut serve: ResMut<Serve>,
+    mut query: Query<(&mut PositionComponent, &mut VelocityComponent), Or<(With<BallComponent>, With<PaddleComponent>)>>,
+) {
+    let stage = match campaign.get_stage() {
+        Some(stage) => stage,
+        None => return,
+    };
+    serve.launch_velocity = stage.lock(serve.launch_velocity);
+    for (mut position, mut velocity) in query.iter_mut() {
+        position.0 = stage.lock(position.0);
+        velocity.0 = stage.lock(velocity.0);
+    }
+}
+
+fn stage_progress_system(
+    mut commands: Commands,
+    mut campaign: ResMut<Campaign>,
+    mut campaign_progress: ResMut<CampaignProgress>,
+    mut paddle_hit_event_reader: EventReader<PaddleHitEvent>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+) {
+    campaign.returns += paddle_hit_event_reader.iter().filter(|paddle_hit_event| paddle_hit_event.0 == Player::Blue).count();
+    campaign.points += score_event_reader.iter().filter(|score_event| score_event.scorer == Player::Blue).count();
+    if campaign.is_passed || !campaign.is_goal_met() {
+        return;
+    }
+    campaign.is_passed = true;
+    match campaign.get_stage() {
+        Some(stage) => {
+            campaign_progress.pass(stage);
+            if let Err(error) = save_ron(&*campaign_progress, get_user_config_path(CAMPAIGN_PROGRESS_FILE)) {
+                println!("Failed to save campaign progress: {}", error);
+            }
+        },
+        None => {
+            // Do nothing
+        }
+    }
+    commands.insert_resource(NextState(PongState::GameOver));
+}
+
+fn stage_ui_system(
+    mut egui_ctx: ResMut<EguiContext>,
+    campaign: Res<Campaign>,
+) {
+    let stage = match campaign.get_stage() {
+        Some(stage) => stage,
+        None => return,
+    };
+    egui::Area::new("campaign-stage")
+        .anchor(egui::Align2::LEFT_CENTER, egui::Vec2::ZERO)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.set_max_width(PROJECTION_AREA_WIDTH);
+            ui.label(egui::RichText::new(stage.title).size(20.).color(egui::Color32::WHITE));
+            ui.label(egui::RichText::new(stage.explanation).color(egui::Color32::WHITE));
+            ui.add_space(CONTROL_HINTS_OFFSET);
+            ui.label(egui::RichText::new(stage.goal.to_string()).color(egui::Color32::LIGHT_BLUE));
+            ui.label(egui::RichText::new(campaign.get_progress_text()).color(egui::Color32::LIGHT_BLUE));
+        });
+}
+
+// End Systems
+
+#[cfg(test)]
+mod test_campaign {
+    use super::*;
+
+    #[test]
+    fn test_stages_add_one_dimension_at_a_time() {
+        assert_eq!(CAMPAIGN[0].dimensions, 2);
+        for pair in CAMPAIGN.windows(2) {
+            assert!(pair[1].dimensions == pair[0].dimensions || pair[1].dimensions == pair[0].dimensions + 1);
+        }
+        assert_eq!(CAMPAIGN[CAMPAIGN.len() - 1].dimensions, 4);
+    }
+
+    #[test]
+    fn test_lock() {
+        let vector = Vec4::new(1., 2., 3., 4.);
+        assert_eq!(CAMPAIGN[0].lock(vector), Vec4::new(1., 0., 0., 4.));
+        assert_eq!(CAMPAIGN[1].lock(vector), Vec4::new(1., 2., 0., 4.));
+        assert_eq!(CAMPAIGN[2].lock(vector), vector);
+    }
+
+    #[test]
+    fn test_progress_unlocks_stages_in_order() {
+        let mut campaign_progress = CampaignProgress::default();
+        assert!(campaign_progress.is_unlocked(0));
+        assert!(!campaign_progress.is_unlocked(1));
+        campaign_progress.pass(&CAMPAIGN[0]);
+        assert!(campaign_progress.is_unlocked(1));
+        assert!(!campaign_progress.is_unlocked(2));
+
+        let contents = ron::ser::to_string_pretty(&campaign_progress, ron::ser::PrettyConfig::new()).unwrap();
+        assert_eq!(ron::from_str::<CampaignProgress>(&contents).unwrap(), campaign_progress);
+    }
+
+    #[test]
+    fn test_goal() {
+        let mut campaign = Campaign { stage_index: Some(0), ..Campaign::default() };
+        assert!(!campaign.is_goal_met());
+        campaign.returns = 5;
+        assert!(campaign.is_goal_met());
+        assert_eq!(campaign.get_next_stage_index(), Some(1));
+
+        let last_stage = Campaign { stage_index: Some(CAMPAIGN.len() - 1), points: 3, ..Campaign::default() };
+        assert!(last_stage.is_goal_met());
+        assert_eq!(last_stage.get_next_stage_index(), None);
+    }
+}
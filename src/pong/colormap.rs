@@ -0,0 +1,257 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::powerups::{ActiveEffects, PowerUpKind};
+use crate::pong::resources::*;
+use crate::pong::ui::get_player_color32;
+
+// Evenly spaced stops from 0 to 1, as sRGB.
+const VIRIDIS: [u32; 9] = [0x440154, 0x472D7B, 0x3B528B, 0x2C728E, 0x21918C, 0x28AE80, 0x5EC962, 0xADDC30, 0xFDE725];
+const CIVIDIS: [u32; 5] = [0x00204D, 0x414D6B, 0x7C7B78, 0xBCAF6F, 0xFFEA46];
+const MAGMA: [u32; 6] = [0x000004, 0x3B0F70, 0x8C2981, 0xDE4968, 0xFE9F6D, 0xFCFDBF];
+const BLUE_WHITE_ORANGE: [u32; 5] = [0x2166AC, 0x92C5DE, 0xF7F7F7, 0xFDB863, 0xE66101];
+// Halfway between blue and going all the way round.
+const HUE_WRAP_THRESHOLD: f32 = 300.;
+
+pub struct ColorMapPlugin;
+
+impl Plugin for ColorMapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ColorMap::default())
+            .add_system(color_legend_system.run_in_state(PongState::InGame));
+    }
+}
+
+// Resources
+
+// How the colour axis is turned into colour.  Everything but Classic is perceptually uniform and readable with the
+// common kinds of colour blindness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMap {
+    Classic,
+    Viridis,
+    Cividis,
+    Magma,
+    BlueWhiteOrange,
+}
+
+impl ColorMap {
+
+    pub const ALL: [ColorMap; 5] = [
+        ColorMap::Classic,
+        ColorMap::Viridis,
+        ColorMap::Cividis,
+        ColorMap::Magma,
+        ColorMap::BlueWhiteOrange,
+    ];
+
+    fn get_stops(&self) -> &'static [u32] {
+        match self {
+            ColorMap::Classic => &[],
+            ColorMap::Viridis => &VIRIDIS,
+            ColorMap::Cividis => &CIVIDIS,
+            ColorMap::Magma => &MAGMA,
+            ColorMap::BlueWhiteOrange => &BLUE_WHITE_ORANGE,
+        }
+    }
+
+    // t runs from 0 at Blue's end of the colour axis to 1 at Red's.
+    pub fn get_color(&self, t: f32) -> Color {
+        let t = t.clamp(0., 1.);
+        match self {
+            ColorMap::Classic => {
+                // A straight run round the hue wheel at full saturation.
+                let blue = Color::BLUE.as_hsla_f32();
+                let red = Color::RED.as_hsla_f32();
+                Color::Hsla {
+                    hue: lerp(blue[0], red[0], t),
+                    saturation: 1.0,
+                    lightness: 0.5,
+                    alpha: blue[3],
+                }
+            },
+            _ => {
+                let stops = self.get_stops();
+                let position = t * (stops.len() - 1) as f32;
+                let index = (position.floor() as usize).min(stops.len() - 2);
+                let from = get_color_from_hex(stops[index]).as_rgba_f32();
+                let to = get_color_from_hex(stops[index + 1]).as_rgba_f32();
+                let fraction = position - index as f32;
+                Color::rgb(
+                    lerp(from[0], to[0], fraction),
+                    lerp(from[1], to[1], fraction),
+                    lerp(from[2], to[2], fraction),
+                )
+            }
+        }
+    }
+
+    // The inverse of get_color, for reading a position back off a colour.
+    pub fn get_t(&self, color: Color) -> f32 {
+        match self {
+            ColorMap::Classic => {
+                let blue = Color::BLUE.as_hsla_f32();
+                let red = Color::RED.as_hsla_f32();
+                let hue = color.as_hsla_f32()[0];
+                // Just past red is back round the wheel near 360.
+                let hue = if hue > HUE_WRAP_THRESHOLD { hue - 360. } else { hue };
+                inverse_lerp(blue[0], red[0], hue)
+            },
+            _ => {
+                // None of the maps cross back over themselves, so the nearest colour along the map will do.
+                let [r, g, b, _] = color.as_rgba_f32();
+                let target = Vec3::new(r, g, b);
+                (0..=COLOR_MAP_INVERSE_SAMPLES)
+                    .map(|sample| sample as f32 / COLOR_MAP_INVERSE_SAMPLES as f32)
+                    .map(|t| {
+                        let [r, g, b, _] = self.get_color(t).as_rgba_f32();
+                        (t, Vec3::new(r, g, b).distance_squared(target))
+                    })
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map_or(0., |(t, _)| t)
+            }
+        }
+    }
+}
+
+impl Default for ColorMap {
+    fn default() -> Self {
+        ColorMap::Classic
+    }
+}
+
+impl Display for ColorMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorMap::Classic => write!(f, "Classic"),
+            ColorMap::Viridis => write!(f, "Viridis"),
+            ColorMap::Cividis => write!(f, "Cividis"),
+            ColorMap::Magma => write!(f, "Magma"),
+            ColorMap::BlueWhiteOrange => write!(f, "Blue-White-Orange"),
+        }
+    }
+}
+
+// End Resources
+
+// Systems
+
+fn color_legend_system(
+    mut egui_ctx: ResMut<EguiContext>,
+    arena: Res<Arena>,
+    color_map: Res<ColorMap>,
+    view_mapping: Res<ViewMapping>,
+    active_effects: Res<ActiveEffects>,
+    ball_query: Query<&PositionComponent, With<BallComponent>>,
+    paddle_query: Query<(&PositionComponent, &PaddleComponent)>,
+) {
+    let color_extent = view_mapping.get_color_extent(&arena);
+    let get_t = |position: Vec4| view_mapping.to_view(position).w / color_extent + 0.5;
+    egui::Area::new("color-legend")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0., -COLOR_LEGEND_OFFSET))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.label(egui::RichText::new(format!("{:?} as Colour ({})", view_mapping.color_axis, *color_map)).color(egui::Color32::WHITE));
+            let (response, painter) = ui.allocate_painter(egui::vec2(COLOR_LEGEND_WIDTH, COLOR_LEGEND_HEIGHT), egui::Sense::hover());
+            let rect = response.rect;
+            let segment_width = rect.width() / COLOR_LEGEND_SEGMENTS as f32;
+            for segment in 0..COLOR_LEGEND_SEGMENTS {
+                let left = rect.left() + segment as f32 * segment_width;
+                let t = (segment as f32 + 0.5) / COLOR_LEGEND_SEGMENTS as f32;
+                painter.rect_filled(
+                    egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(left + segment_width, rect.bottom())),
+                    0.,
+                    get_color32(color_map.get_color(t)),
+                );
+            }
+            let get_x = |t: f32| rect.left() + t.clamp(0., 1.) * rect.width();
+            for (position, paddle_component) in paddle_query.iter() {
+                let x = get_x(get_t(position.0));
+                painter.line_segment(
+                    [egui::pos2(x, rect.top() - COLOR_LEGEND_MARKER_OVERHANG), egui::pos2(x, rect.bottom() + COLOR_LEGEND_MARKER_OVERHANG)],
+                    egui::Stroke::new(3., get_player_color32(paddle_component.0)),
+                );
+            }
+            // The legend would give away what the grey ball is hiding.
+            if !active_effects.is_active(PowerUpKind::WInvisibleBall) {
+                for position in ball_query.iter() {
+                    let center = egui::pos2(get_x(get_t(position.0)), rect.center().y);
+                    painter.circle(center, COLOR_LEGEND_HEIGHT / 3., egui::Color32::BLACK, egui::Stroke::new(2., egui::Color32::WHITE));
+                }
+            }
+        });
+}
+
+// End Systems
+
+// Helper Functions
+
+fn get_color_from_hex(hex: u32) -> Color {
+    Color::rgb_u8((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+pub fn get_color32(color: Color) -> egui::Color32 {
+    let [r, g, b, a] = color.as_rgba_f32();
+    egui::Color32::from_rgba_unmultiplied((r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8, (a * 255.) as u8)
+}
+
+// Written this way round so that t = 1 lands exactly on b.
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a * (1. - t) + b * t
+}
+
+fn inverse_lerp(a: f32, b: f32, value: f32) -> f32 {
+    (value - a) / (b - a)
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_colormap {
+    use super::*;
+
+    fn get_luminance(color: Color) -> f32 {
+        let [r, g, b, _] = color.as_linear_rgba_f32();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    #[test]
+    fn test_classic_runs_from_blue_to_red() {
+        assert_eq!(ColorMap::Classic.get_color(0.), Color::BLUE.as_hsla());
+        assert_eq!(ColorMap::Classic.get_color(1.), Color::RED.as_hsla());
+    }
+
+    #[test]
+    fn test_stops_are_hit_exactly() {
+        assert_eq!(ColorMap::Viridis.get_color(0.), get_color_from_hex(VIRIDIS[0]));
+        assert_eq!(ColorMap::Viridis.get_color(1.), get_color_from_hex(VIRIDIS[VIRIDIS.len() - 1]));
+        assert_eq!(ColorMap::BlueWhiteOrange.get_color(0.5), get_color_from_hex(0xF7F7F7));
+    }
+
+    #[test]
+    fn test_get_t_inverts_get_color() {
+        for color_map in ColorMap::ALL {
+            for i in 0..=10 {
+                let t = i as f32 / 10.;
+                let recovered = color_map.get_t(color_map.get_color(t));
+                assert!((recovered - t).abs() < 0.01, "{} gave {} back for {}", color_map, recovered, t);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequential_maps_get_brighter() {
+        for color_map in [ColorMap::Viridis, ColorMap::Cividis, ColorMap::Magma] {
+            for i in 0..20 {
+                let darker = get_luminance(color_map.get_color(i as f32 / 20.));
+                let brighter = get_luminance(color_map.get_color((i + 1) as f32 / 20.));
+                assert!(brighter > darker, "{} isn't getting brighter at step {}", color_map, i);
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter};
+
 use bevy::prelude::*;
 use crate::pong::player::*;
 use crate::pong::rotations::Rotation;
@@ -5,24 +7,62 @@ use crate::pong::rotations::Rotation;
 pub type Speed = f32;
 pub type SizeModifier = f32;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlScheme {
+    Keyboard,
+    Mouse,
+    Gamepad,
+}
+
+impl Display for ControlScheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ControlScheme::Keyboard => write!(f, "Keyboard"),
+            ControlScheme::Mouse => write!(f, "Mouse"),
+            ControlScheme::Gamepad => write!(f, "Gamepad"),
+        }
+    }
+}
+
 #[derive(Component, Clone, Copy)]
-pub struct PlayerInputComponent;
+pub struct PlayerInputComponent(pub ControlScheme);
 
 #[derive(Component, Clone, Copy)]
 pub struct AIComponent(pub Speed);
 
+// Where an AI last worked out the ball would arrive, in its own goal frame.  Stepping through force fields is too slow to
+// redo every frame.
+#[derive(Component, Clone, Copy, Default)]
+pub struct PredictionComponent {
+    pub arrival: Option<(Vec4, Vec4)>,
+    // Seconds since the arrival was worked out, or None if it needs working out again.
+    pub age: Option<f32>,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct MainCameraComponent;
+
 #[derive(Component, Clone, Copy)]
 pub struct BallComponent;
 
 #[derive(Component, Clone, Copy)]
 pub struct PaddleComponent(pub Player);
 
+#[derive(Component, Clone, Copy)]
+pub struct ServeMarkerComponent;
+
 #[derive(Component, Clone, Copy)]
 pub struct GoalComponent;
 
 #[derive(Component, Clone, Copy)]
 pub struct WallComponent;
 
+#[derive(Component, Clone, Copy)]
+pub struct ArenaComponent;
+
+#[derive(Component, Clone, Copy)]
+pub struct ArenaRectangleComponent;
+
 #[derive(Component, Clone, Copy)]
 pub struct PositionComponent(pub Vec4);
 
@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// The folder under the platform's config directory that player settings are saved in.
+pub const USER_CONFIG_FOLDER: &str = "four-dimensional-pong";
+// Used when none of the usual environment variables are set.
+pub const FALLBACK_CONFIG_PATH: &str = "config";
+
+#[derive(Debug)]
+pub enum RonError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl Display for RonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RonError::Io(error) => write!(f, "Could not access the file: {}", error),
+            RonError::Parse(error) => write!(f, "Could not parse the file: {}", error),
+            RonError::Serialize(error) => write!(f, "Could not write the file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RonError {}
+
+// Helper Functions
+
+pub fn parse_ron<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, RonError> {
+    ron::de::from_bytes(bytes).map_err(RonError::Parse)
+}
+
+pub fn load_ron<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, RonError> {
+    let bytes = std::fs::read(path).map_err(RonError::Io)?;
+    parse_ron(&bytes)
+}
+
+// Creates the folder first, since a player's config folder won't exist until something is saved to it.
+pub fn save_ron<T: Serialize>(value: &T, path: impl AsRef<Path>) -> Result<(), RonError> {
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new()).map_err(RonError::Serialize)?;
+    if let Some(parent) = path.as_ref().parent() {
+        std::fs::create_dir_all(parent).map_err(RonError::Io)?;
+    }
+    std::fs::write(path, contents).map_err(RonError::Io)
+}
+
+// Where a player's own copy of a settings file lives, so the defaults shipped in config/ are never written to.
+pub fn get_user_config_path(file_name: &str) -> PathBuf {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    match config_dir {
+        Some(config_dir) => config_dir.join(USER_CONFIG_FOLDER).join(file_name),
+        None => PathBuf::from(FALLBACK_CONFIG_PATH).join(file_name),
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_config {
+    use super::*;
+
+    #[test]
+    fn test_user_config_path_is_outside_the_shipped_config() {
+        let path = get_user_config_path("bindings.ron");
+        assert!(path.ends_with("bindings.ron"));
+        if std::env::var_os("HOME").is_some() {
+            assert!(path.parent().unwrap().ends_with(USER_CONFIG_FOLDER));
+        }
+    }
+
+    #[test]
+    fn test_ron_save_and_load() {
+        let path = std::env::temp_dir().join("four-dimensional-pong-test-config").join("numbers.ron");
+        let numbers = vec![1, 2, 3];
+        save_ron(&numbers, &path).unwrap();
+        assert_eq!(load_ron::<Vec<i32>>(&path).unwrap(), numbers);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(load_ron::<Vec<i32>>(&path), Err(RonError::Io(_))));
+        assert!(matches!(parse_ron::<Vec<i32>>(b"(name: 3)"), Err(RonError::Parse(_))));
+    }
+}
@@ -5,6 +5,7 @@ pub const GOAL_OFFSET_FROM_ARENA: f32 = 0.1;
 
 pub const PROJECTION_AREA_WIDTH: f32 = 300.0;
 pub const PROJECTION_AREA_HEIGHT: f32 = 200.0;
+pub const PROJECTION_GRID_LABEL_HEIGHT: f32 = 30.0;
 pub const Y_OFFSET_FOR_PROJECTIONS: f32 = -100.0;
 pub const DELTA_X_FOR_PROJECTIONS: f32 = 100.0;
 pub const EXTRA_ZOOMOUT_FOR_PROJECTIONS: f32 = 3.0;
@@ -21,5 +22,113 @@ pub const CLAMP_PADDING: f32 = 0.0001;
 pub const AI_PADDLE_SPEED_EASY: f32 = 0.5;
 pub const AI_PADDLE_SPEED_MEDIUM: f32 = 1.0;
 pub const AI_PADDLE_SPEED_HARD: f32 = PADDLE_SPEED;
+pub const PADDLE_DEFLECTION_SPEED: f32 = 1.0;
+pub const BALL_MAX_TRANSVERSE_SPEED: f32 = 2.0;
+pub const AIM_ASSIST_STRENGTH: f32 = 0.5;
+// Slow enough that a paddle and ball closing on each other can't pass through one another in a frame.
+pub const PADDLE_W_SPEED: f32 = 1.0;
+pub const DEFENCE_ZONE_DEPTH_DEFAULT: f32 = 1.0;
+// The zones stop short of the centre so that each paddle stays on its own side.
+pub const DEFENCE_ZONE_CENTRE_GAP: f32 = 0.25;
 
-pub const PAUSE_SCREEN_SPACING: f32 = 50.;
\ No newline at end of file
+pub const SERVE_COUNTDOWN_SECONDS: f32 = 3.0;
+pub const SERVE_BALL_OFFSET: f32 = 3. * BALL_RADIUS;
+pub const SERVE_TRANSVERSE_SPEED: f32 = 1.0;
+pub const SERVE_MARKER_DISTANCE: f32 = 0.3;
+pub const SERVE_MARKER_SCALE: f32 = 0.5;
+pub const SERVES_PER_TURN_DEFAULT: usize = 2;
+
+pub const POWER_UP_SPAWN_SECONDS: f32 = 8.0;
+pub const POWER_UP_MAX_COUNT: usize = 2;
+pub const POWER_UP_RADIUS: f32 = 0.08;
+pub const POWER_UP_DURATION_SECONDS: f32 = 8.0;
+pub const POWER_UP_PADDLE_GROWTH: f32 = 1.5;
+pub const POWER_UP_SLOW_BALL_FACTOR: f32 = 0.5;
+
+pub const OBSTACLE_ALPHA: f32 = 0.6;
+
+pub const GHOST_BALL_DISTANCE: f32 = 0.2;
+pub const GHOST_BALL_ALPHA: f32 = 0.4;
+
+pub const SQUASH_SPEED_UP_PER_RETURN: f32 = 0.05;
+pub const SQUASH_MAX_SPEED: f32 = 3.0;
+
+// Time ticks at the same rate as the ball's usual speed along w.
+pub const SPACETIME_CLOCK_RATE: f32 = 1.0;
+pub const SPACETIME_WORLD_LINE_INTERVAL: f32 = 0.05;
+pub const SPACETIME_WORLD_LINE_LENGTH: usize = 40;
+pub const SPACETIME_WORLD_LINE_RADIUS: f32 = 0.5 * BALL_RADIUS;
+
+pub const RELATIVITY_SPEED_OF_LIGHT_DEFAULT: f32 = 3.0;
+// The ball's speed along w has to stay below it.
+pub const RELATIVITY_MIN_SPEED_OF_LIGHT: f32 = 1.5;
+pub const RELATIVITY_MAX_SPEED_OF_LIGHT: f32 = 10.0;
+pub const RELATIVITY_MAX_BETA: f32 = 0.99;
+
+pub const TRAJECTORY_SECONDS_DEFAULT: f32 = 2.0;
+pub const TRAJECTORY_MIN_SECONDS: f32 = 0.5;
+pub const TRAJECTORY_MAX_SECONDS: f32 = 5.0;
+pub const TRAJECTORY_DOT_INTERVAL: f32 = 0.1;
+pub const TRAJECTORY_MAX_DOTS: usize = (TRAJECTORY_MAX_SECONDS / TRAJECTORY_DOT_INTERVAL) as usize;
+pub const TRAJECTORY_DOT_RADIUS: f32 = 0.5 * BALL_RADIUS;
+pub const TRAJECTORY_MARKER_RADIUS: f32 = 2. * BALL_RADIUS;
+
+pub const COLOR_MAP_INVERSE_SAMPLES: usize = 256;
+pub const COLOR_LEGEND_WIDTH: f32 = PROJECTION_AREA_WIDTH;
+pub const COLOR_LEGEND_HEIGHT: f32 = 16.0;
+pub const COLOR_LEGEND_SEGMENTS: usize = 64;
+pub const COLOR_LEGEND_MARKER_OVERHANG: f32 = 4.0;
+// Just above the middle projection panel.
+pub const COLOR_LEGEND_OFFSET: f32 = PROJECTION_AREA_HEIGHT + 2. * PROJECTION_GRID_LABEL_HEIGHT;
+
+pub const BREAKOUT_LIVES: usize = 3;
+pub const BREAKOUT_MAX_CELLS: usize = 8;
+
+pub const FORCE_FIELD_STEP: f32 = 1. / 240.;
+pub const FORCE_FIELD_SOFTENING: f32 = 0.2;
+pub const FORCE_FIELD_PREDICTION_SECONDS: f32 = 10.;
+pub const FORCE_FIELD_PREDICTION_REFRESH_SECONDS: f32 = 0.5;
+pub const FORCE_FIELD_MAX_GRAVITY: f32 = 2.;
+pub const FORCE_FIELD_MAX_DRAG: f32 = 1.;
+pub const FORCE_FIELD_MAX_STRENGTH: f32 = 1.;
+
+// Beyond these the arena no longer fits the camera or the projection panels.
+pub const LEVEL_MIN_ARENA_LENGTH: f32 = 1.0;
+pub const LEVEL_MAX_ARENA_LENGTH: f32 = 2. * ARENA_LENGTH;
+pub const LEVEL_MIN_ARENA_WIDTH: f32 = 0.5;
+pub const LEVEL_MAX_ARENA_WIDTH: f32 = 2. * ARENA_WIDTH;
+pub const LEVEL_MIN_GOAL_WIDTH: f32 = 4. * BALL_RADIUS;
+pub const LEVEL_MIN_PADDLE_SIZE: f32 = 0.5;
+
+pub const EDITOR_SNAP_STEP_DEFAULT: f32 = 0.1;
+pub const EDITOR_MAX_UNDO: usize = 100;
+pub const EDITOR_COPY_SUFFIX: &str = "-copy";
+pub const EDITOR_NEW_OBSTACLE_SIZE: f32 = 0.2;
+pub const EDITOR_DRAG_SPEED: f32 = 0.01;
+// Roughly how far one pixel of a projection panel is in the arena.
+pub const EDITOR_PANEL_UNITS_PER_PIXEL: f32 = ARENA_LENGTH / PROJECTION_AREA_WIDTH;
+
+pub const PERCEPTION_MAIN_VIEW_NOISE: f32 = 0.05;
+pub const PERCEPTION_PROJECTION_NOISE: f32 = 0.02;
+pub const PERCEPTION_COLOR_NOISE: f32 = 0.01;
+pub const PERCEPTION_COLOR_STEP: f32 = 1.0 / 255.0;
+pub const PERCEPTION_PIXEL_SIZE: f32 = ARENA_LENGTH / PROJECTION_AREA_WIDTH;
+pub const PERCEPTION_VELOCITY_SMOOTHING: f32 = 0.2;
+pub const PERCEPTION_RESET_DISTANCE: f32 = 0.5;
+
+pub const MOUSE_SENSITIVITY_DEFAULT: f32 = 1.0;
+pub const MOUSE_SMOOTHING_DEFAULT: f32 = 0.5;
+pub const MOUSE_MAX_SMOOTHING: f32 = 0.95;
+pub const MOUSE_SCROLL_STEP: f32 = 0.1;
+pub const MOUSE_PIXELS_PER_SCROLL_LINE: f32 = 100.0;
+
+pub const GAMEPAD_DEADZONE_DEFAULT: f32 = 0.15;
+pub const GAMEPAD_RESPONSE_EXPONENT_DEFAULT: f32 = 2.0;
+
+pub const MATCH_POINTS_TO_WIN_DEFAULT: usize = 11;
+pub const MATCH_MAX_POINTS_TO_WIN: usize = 21;
+pub const MATCH_MAX_SETS_TO_WIN: usize = 3;
+pub const MATCH_MAX_SERVES_PER_TURN: usize = 5;
+
+pub const PAUSE_SCREEN_SPACING: f32 = 50.;
+pub const CONTROL_HINTS_OFFSET: f32 = 30.;
@@ -0,0 +1,269 @@
+use std::hash::Hash;
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::pong::actions::{Action, PlayerAction};
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::player::Player;
+use crate::pong::pong::{get_velocity_towards, get_w_velocity};
+use crate::pong::powerups::ActiveEffects;
+use crate::pong::resources::*;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(MouseSettings::default())
+            .insert_resource(AnalogSettings::default())
+            .add_system(gamepad_connection_system)
+            .add_system(mouse_control_system.run_in_state(PongState::InGame))
+            .add_system(gamepad_control_system.run_in_state(PongState::InGame));
+    }
+}
+
+// Systems
+
+fn mouse_control_system(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    mouse_settings: Res<MouseSettings>,
+    view_mapping: Res<ViewMapping>,
+    arena: Res<Arena>,
+    defence_zone: Res<DefenceZone>,
+    active_effects: Res<ActiveEffects>,
+    action_input: Res<Input<PlayerAction>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut mouse_target: Local<Option<Vec3>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCameraComponent>>,
+    mut paddle_query: Query<(&PositionComponent, &mut VelocityComponent, &PaddleComponent, &PlayerInputComponent)>,
+) {
+    let scroll: f32 = mouse_wheel_events.iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / MOUSE_PIXELS_PER_SCROLL_LINE,
+        })
+        .sum();
+    let cursor_position = windows.get_primary().and_then(|window| window.cursor_position());
+    let (camera, camera_transform) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    for (position, mut velocity, paddle_component, player_input) in paddle_query.iter_mut() {
+        if player_input.0 != ControlScheme::Mouse {
+            continue;
+        }
+        let player = paddle_component.0;
+        let z_input = get_axis_input(&action_input, PlayerAction(player, Action::MoveZPos), PlayerAction(player, Action::MoveZNeg));
+        let paddle_position = position.0.truncate();
+        let mut target = mouse_target.unwrap_or(paddle_position);
+        target.z += z_input * PADDLE_SPEED * time.delta_seconds() + scroll * MOUSE_SCROLL_STEP;
+
+        let maybe_cursor_point = cursor_position
+            .and_then(|cursor_position| get_cursor_ray(camera, camera_transform, cursor_position))
+            .and_then(|(origin, direction)| intersect_ray_with_z_plane(origin, direction, view_mapping.to_view(position.0).z));
+        match maybe_cursor_point {
+            Some(cursor_point) => {
+                let aimed_point = cursor_point.truncate() * mouse_settings.sensitivity * active_effects.get_control_sign(player);
+                let blend = get_smoothing_blend(mouse_settings.smoothing, time.delta_seconds());
+                let smoothed_point = target.truncate().lerp(aimed_point, blend);
+                target = smoothed_point.extend(target.z);
+            },
+            None => {
+                // Cursor is outside the window or parallel to the paddle's plane, so hold the current target.
+            }
+        }
+
+        target = target.clamp(-Vec3::ONE * arena.width/2., Vec3::ONE * arena.width/2.);
+        *mouse_target = Some(target);
+        velocity.0 = get_velocity_towards(paddle_position, target, PADDLE_SPEED, time.delta_seconds())
+            .extend(get_w_velocity(&action_input, player, &defence_zone));
+    }
+}
+
+fn gamepad_connection_system(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut gamepad_assignments: ResMut<GamepadAssignments>,
+) {
+    for GamepadEvent(gamepad, event_type) in gamepad_events.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                let maybe_free_player = Player::HEAD_TO_HEAD.iter()
+                    .find(|player| gamepad_assignments.get(**player).is_none());
+                match maybe_free_player {
+                    Some(player) => {
+                        gamepad_assignments.assign(*player, *gamepad);
+                    },
+                    None => {
+                        // Every player already has a gamepad.
+                    }
+                }
+            },
+            GamepadEventType::Disconnected => {
+                gamepad_assignments.unassign(*gamepad);
+            },
+            _ => {
+                // Button and axis changes are read from the Input and Axis resources.
+            }
+        }
+    }
+}
+
+fn gamepad_control_system(
+    analog_settings: Res<AnalogSettings>,
+    defence_zone: Res<DefenceZone>,
+    active_effects: Res<ActiveEffects>,
+    action_input: Res<Input<PlayerAction>>,
+    gamepad_assignments: Res<GamepadAssignments>,
+    axes: Res<Axis<GamepadAxis>>,
+    button_axes: Res<Axis<GamepadButton>>,
+    mut paddle_query: Query<(&PaddleComponent, &mut VelocityComponent, &PlayerInputComponent)>,
+) {
+    for (paddle_component, mut velocity, player_input) in paddle_query.iter_mut() {
+        if player_input.0 != ControlScheme::Gamepad {
+            continue;
+        }
+        let gamepad = match gamepad_assignments.get(paddle_component.0) {
+            Some(gamepad) => gamepad,
+            None => {
+                velocity.0 = Vec4::ZERO;
+                continue;
+            }
+        };
+        let get_axis = |axis_type| axes.get(GamepadAxis(gamepad, axis_type)).unwrap_or(0.);
+        let get_button_axis = |button_type| button_axes.get(GamepadButton(gamepad, button_type)).unwrap_or(0.);
+
+        let stick = apply_stick_response(
+            Vec2::new(get_axis(GamepadAxisType::LeftStickX), get_axis(GamepadAxisType::LeftStickY)),
+            analog_settings.deadzone,
+            analog_settings.response_exponent,
+        );
+        let trigger_z = get_button_axis(GamepadButtonType::RightTrigger2) - get_button_axis(GamepadButtonType::LeftTrigger2);
+        let stick_z = get_axis(GamepadAxisType::RightStickY);
+        let raw_z = if trigger_z.abs() > stick_z.abs() { trigger_z } else { stick_z };
+        let z = apply_analog_response(raw_z, analog_settings.deadzone, analog_settings.response_exponent);
+
+        // The bumpers come through the action input, so w works the same way as on the keyboard.
+        let w_velocity = get_w_velocity(&action_input, paddle_component.0, &defence_zone);
+        let control_sign = active_effects.get_control_sign(paddle_component.0);
+        velocity.0 = (control_sign * PADDLE_SPEED * Vec3::new(stick.x, stick.y, z)).extend(w_velocity);
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+pub fn get_axis_input<T: Copy + Eq + Hash>(action_input: &Input<T>, positive: T, negative: T) -> f32 {
+    let mut axis = 0.;
+    if action_input.pressed(positive) {
+        axis += 1.;
+    }
+    if action_input.pressed(negative) {
+        axis -= 1.;
+    }
+    axis
+}
+
+pub fn get_cursor_ray(camera: &Camera, camera_transform: &GlobalTransform, cursor_position: Vec2) -> Option<(Vec3, Vec3)> {
+    let viewport_size = camera.logical_viewport_size()?;
+    let ndc = (cursor_position / viewport_size) * 2. - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+    // Bevy uses a reversed depth buffer, so the near plane is at depth 1.
+    let near_point = ndc_to_world.project_point3(ndc.extend(1.));
+    let further_point = ndc_to_world.project_point3(ndc.extend(0.5));
+    Some((near_point, (further_point - near_point).normalize()))
+}
+
+pub fn intersect_ray_with_z_plane(origin: Vec3, direction: Vec3, plane_z: f32) -> Option<Vec3> {
+    if direction.z.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = (plane_z - origin.z) / direction.z;
+    if distance < 0. {
+        return None;
+    }
+    Some(origin + direction * distance)
+}
+
+fn get_smoothing_blend(smoothing: f32, delta_seconds: f32) -> f32 {
+    // Smoothing is expressed per 60Hz frame so that it feels the same at any frame rate.
+    1. - smoothing.clamp(0., MOUSE_MAX_SMOOTHING).powf(delta_seconds * 60.)
+}
+
+pub fn apply_analog_response(value: f32, deadzone: f32, exponent: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= deadzone {
+        return 0.;
+    }
+    let rescaled_magnitude = ((magnitude - deadzone) / (1. - deadzone)).min(1.);
+    value.signum() * rescaled_magnitude.powf(exponent)
+}
+
+pub fn apply_stick_response(stick: Vec2, deadzone: f32, exponent: f32) -> Vec2 {
+    // Use a radial deadzone so that diagonals aren't cut off.
+    let magnitude = stick.length();
+    if magnitude <= deadzone {
+        return Vec2::ZERO;
+    }
+    stick / magnitude * apply_analog_response(magnitude, deadzone, exponent)
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_controls {
+    use super::*;
+
+    #[test]
+    fn test_intersect_ray_with_z_plane() {
+        assert_eq!(intersect_ray_with_z_plane(Vec3::new(0., 0., 2.), -Vec3::Z, 0.5), Some(Vec3::new(0., 0., 0.5)));
+        assert_eq!(intersect_ray_with_z_plane(Vec3::new(0., 0., 2.), Vec3::Z, 0.5), None);
+        assert_eq!(intersect_ray_with_z_plane(Vec3::new(0., 0., 2.), Vec3::X, 0.5), None);
+
+        let direction = Vec3::new(1., 1., -1.).normalize();
+        let intersection = intersect_ray_with_z_plane(Vec3::new(0., 0., 1.), direction, 0.).unwrap();
+        assert!(intersection.distance(Vec3::new(1., 1., 0.)) < 0.0001);
+    }
+
+    #[test]
+    fn test_get_smoothing_blend() {
+        assert_eq!(get_smoothing_blend(0., 1. / 60.), 1.);
+        assert!((get_smoothing_blend(0.5, 1. / 60.) - 0.5).abs() < 0.0001);
+        assert!(get_smoothing_blend(MOUSE_MAX_SMOOTHING + 1., 1. / 60.) > 0.);
+    }
+
+    #[test]
+    fn test_apply_analog_response() {
+        assert_eq!(apply_analog_response(0.1, 0.15, 2.), 0.);
+        assert_eq!(apply_analog_response(-0.15, 0.15, 2.), 0.);
+        assert_eq!(apply_analog_response(1., 0.15, 2.), 1.);
+        assert_eq!(apply_analog_response(-1., 0.15, 2.), -1.);
+        assert!((apply_analog_response(0.575, 0.15, 2.) - 0.25).abs() < 0.0001);
+        assert!((apply_analog_response(-0.575, 0.15, 1.) + 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_apply_stick_response() {
+        assert_eq!(apply_stick_response(Vec2::new(0.1, 0.1), 0.15, 2.), Vec2::ZERO);
+        let diagonal = apply_stick_response(Vec2::new(1., 1.).normalize(), 0.15, 2.);
+        assert!((diagonal.length() - 1.).abs() < 0.0001);
+        assert!((diagonal.x - diagonal.y).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_get_axis_input() {
+        let mut action_input = Input::<Action>::default();
+        assert_eq!(get_axis_input(&action_input, Action::MoveZPos, Action::MoveZNeg), 0.);
+        action_input.press(Action::MoveZPos);
+        assert_eq!(get_axis_input(&action_input, Action::MoveZPos, Action::MoveZNeg), 1.);
+        action_input.press(Action::MoveZNeg);
+        assert_eq!(get_axis_input(&action_input, Action::MoveZPos, Action::MoveZNeg), 0.);
+        action_input.release(Action::MoveZPos);
+        assert_eq!(get_axis_input(&action_input, Action::MoveZPos, Action::MoveZNeg), -1.);
+    }
+}
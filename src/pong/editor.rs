@@ -0,0 +1,719 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+use crate::pong::breakout::BrickGrid;
+use crate::pong::components::*;
+use crate::pong::fields::ForceFields;
+use crate::pong::constants::*;
+use crate::pong::controls::{get_cursor_ray, intersect_ray_with_z_plane};
+use crate::pong::level::*;
+use crate::pong::obstacles::{Obstacle, ObstacleLayout, ObstacleShape};
+use crate::pong::player::Player;
+use crate::pong::resources::*;
+use crate::pong::rules::{BaseMatchRules, MatchRules};
+use crate::pong::ui::{get_player_color32, instantiate_projection_panel, toggle_button};
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(EditorState::default())
+            .add_enter_system(PongState::Editor, editor_enter_system)
+            .add_enter_system(PongState::Menu, end_test_run_system)
+            .add_system(editor_preview_system.run_in_state(PongState::Editor))
+            .add_system(editor_mouse_system.run_in_state(PongState::Editor))
+            .add_system(editor_shortcut_system.run_in_state(PongState::Editor))
+            .add_system(editor_ui_system.run_in_state(PongState::Editor))
+            .add_system(test_run_ui_system.run_in_state(PongState::Paused))
+            .add_system(test_run_ui_system.run_in_state(PongState::GameOver));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorSelection {
+    Obstacle(usize),
+    Spawn(Player),
+}
+
+impl Display for EditorSelection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditorSelection::Obstacle(index) => write!(f, "Obstacle {}", index + 1),
+            EditorSelection::Spawn(player) => write!(f, "{} Spawn", player),
+        }
+    }
+}
+
+// Whole-level snapshots: levels are tiny, and it means every kind of edit can be undone the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditHistory {
+    undo_stack: Vec<Level>,
+    redo_stack: Vec<Level>,
+    committed: Level,
+}
+
+impl EditHistory {
+
+    pub fn new(level: &Level) -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            committed: level.clone(),
+        }
+    }
+
+    pub fn commit(&mut self, level: &Level) {
+        if *level == self.committed {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.committed, level.clone());
+        self.undo_stack.push(previous);
+        if self.undo_stack.len() > EDITOR_MAX_UNDO {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<Level> {
+        let level = self.undo_stack.pop()?;
+        let current = std::mem::replace(&mut self.committed, level.clone());
+        self.redo_stack.push(current);
+        Some(level)
+    }
+
+    pub fn redo(&mut self) -> Option<Level> {
+        let level = self.redo_stack.pop()?;
+        let current = std::mem::replace(&mut self.committed, level.clone());
+        self.undo_stack.push(current);
+        Some(level)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+// Resources
+
+pub struct EditorState {
+    pub level: Level,
+    pub file_name: String,
+    pub selection: Option<EditorSelection>,
+    pub placement_w: f32,
+    pub is_snapping: bool,
+    pub snap_step: f32,
+    pub is_test_running: bool,
+    pub status: String,
+    history: EditHistory,
+    drag_start: Option<Vec4>,
+    drag_offset: Vec4,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        let level = Level::default();
+        Self {
+            history: EditHistory::new(&level),
+            level,
+            file_name: "custom".to_string(),
+            selection: None,
+            placement_w: 0.,
+            is_snapping: true,
+            snap_step: EDITOR_SNAP_STEP_DEFAULT,
+            is_test_running: false,
+            status: String::new(),
+            drag_start: None,
+            drag_offset: Vec4::ZERO,
+        }
+    }
+}
+
+impl EditorState {
+
+    pub fn open(&mut self, level: Level, file_name: String) {
+        *self = Self {
+            history: EditHistory::new(&level),
+            level,
+            file_name,
+            ..Default::default()
+        };
+    }
+
+    pub fn undo(&mut self) {
+        match self.history.undo() {
+            Some(level) => self.restore(level),
+            None => {
+                // Do nothing
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        match self.history.redo() {
+            Some(level) => self.restore(level),
+            None => {
+                // Do nothing
+            }
+        }
+    }
+
+    pub fn begin_drag(&mut self) {
+        self.drag_start = self.selection.and_then(|selection| get_selection_position(&self.level, selection));
+        self.drag_offset = Vec4::ZERO;
+    }
+
+    pub fn drag_by(&mut self, offset: Vec4) {
+        self.drag_offset += offset;
+        match (self.selection, self.drag_start) {
+            (Some(selection), Some(drag_start)) => {
+                let position = self.snap(drag_start + self.drag_offset);
+                set_selection_position(&mut self.level, selection, position);
+            },
+            _ => {
+                // Do nothing
+            }
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_start = None;
+        // A whole drag is one step in the history.
+        self.history.commit(&self.level);
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    pub fn snap(&self, position: Vec4) -> Vec4 {
+        if self.is_snapping {
+            snap(position, self.snap_step)
+        } else {
+            position
+        }
+    }
+
+    fn restore(&mut self, level: Level) {
+        self.level = level;
+        // The selected obstacle might not exist any more.
+        if let Some(EditorSelection::Obstacle(index)) = self.selection {
+            if index >= self.level.obstacles.len() {
+                self.selection = None;
+            }
+        }
+    }
+}
+
+// End Resources
+
+// Systems
+
+fn editor_enter_system(
+    mut editor: ResMut<EditorState>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+    mut obstacle_layout: ResMut<ObstacleLayout>,
+    mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent), With<BallComponent>>,
+) {
+    if editor.is_test_running {
+        // Back from a test run, so carry on where we left off.
+        editor.is_test_running = false;
+    } else {
+        let level = level_assets.get(&levels.selected).cloned().unwrap_or_default();
+        let file_name = asset_server.get_handle_path(&levels.selected)
+            .and_then(|path| path.path().file_name().map(|file_name| file_name.to_string_lossy().to_string()))
+            .map_or("custom".to_string(), |file_name| file_name.trim_end_matches(LEVEL_EXTENSION).to_string());
+        editor.open(level, get_editable_file_name(file_name));
+    }
+    // Make sure the obstacles get respawned even if the layout is the same.
+    obstacle_layout.0 = editor.level.obstacles.clone();
+    for (mut ball_position, mut ball_velocity) in ball_query.iter_mut() {
+        ball_position.0 = Vec4::ZERO;
+        ball_velocity.0 = Vec4::ZERO;
+    }
+}
+
+fn editor_preview_system(
+    editor: Res<EditorState>,
+    mut arena: ResMut<Arena>,
+    mut obstacle_layout: ResMut<ObstacleLayout>,
+    mut paddle_query: Query<(&mut PositionComponent, &PaddleComponent)>,
+) {
+    if *arena != editor.level.arena {
+        *arena = editor.level.arena.clone();
+    }
+    if obstacle_layout.0 != editor.level.obstacles {
+        obstacle_layout.0 = editor.level.obstacles.clone();
+    }
+    // The paddles stand in for the spawn points.
+    for (mut paddle_position, paddle_component) in paddle_query.iter_mut() {
+        paddle_position.0 = editor.level.arena.get_paddle_starting_position(paddle_component.0);
+    }
+}
+
+fn editor_mouse_system(
+    windows: Res<Windows>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut editor: ResMut<EditorState>,
+    mut is_dragging_in_view: Local<bool>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCameraComponent>>,
+) {
+    let maybe_ray = match (windows.get_primary().and_then(|window| window.cursor_position()), camera_query.get_single()) {
+        (Some(cursor_position), Ok((camera, camera_transform))) => get_cursor_ray(camera, camera_transform, cursor_position),
+        _ => None,
+    };
+    let (origin, direction) = match maybe_ray {
+        Some(ray) => ray,
+        None => return,
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) && !egui_ctx.ctx_mut().is_pointer_over_area() {
+        editor.selection = pick_selection(&editor.level, origin, direction);
+        editor.begin_drag();
+        *is_dragging_in_view = editor.is_dragging();
+    }
+    if !*is_dragging_in_view {
+        return;
+    }
+    if mouse_input.just_released(MouseButton::Left) {
+        *is_dragging_in_view = false;
+        editor.end_drag();
+        return;
+    }
+
+    // Slide the selection around the plane it's in, like the mouse control scheme does with paddles.
+    let start = editor.drag_start.unwrap_or(Vec4::ZERO);
+    match intersect_ray_with_z_plane(origin, direction, start.z) {
+        Some(cursor_point) => {
+            let offset = cursor_point.extend(start.w) - start - editor.drag_offset;
+            editor.drag_by(offset);
+        },
+        None => {
+            // Do nothing
+        }
+    }
+}
+
+fn editor_shortcut_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut editor: ResMut<EditorState>,
+) {
+    if egui_ctx.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let is_ctrl_held = keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let is_shift_held = keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    if is_ctrl_held && keyboard_input.just_pressed(KeyCode::Z) {
+        if is_shift_held {
+            editor.redo();
+        } else {
+            editor.undo();
+        }
+    } else if is_ctrl_held && keyboard_input.just_pressed(KeyCode::Y) {
+        editor.redo();
+    } else if keyboard_input.just_pressed(KeyCode::Delete) {
+        delete_selection(&mut editor);
+    }
+}
+
+fn editor_ui_system(
+    mut commands: Commands,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut editor: ResMut<EditorState>,
+    mut levels: ResMut<Levels>,
+    asset_server: Res<AssetServer>,
+    projection_images: Res<ProjectionImages>,
+    mut arena: ResMut<Arena>,
+    mut obstacle_layout: ResMut<ObstacleLayout>,
+    base_match_rules: Res<BaseMatchRules>,
+    mut match_rules: ResMut<MatchRules>,
+    mut brick_grid: ResMut<BrickGrid>,
+    mut force_fields: ResMut<ForceFields>,
+) {
+    let editor = &mut *editor;
+    let validation = editor.level.validate();
+    egui::SidePanel::left("editor-panel").show(egui_ctx.ctx_mut(), |ui| {
+        ui.heading("Level Editor");
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut editor.level.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut editor.file_name);
+            ui.label(LEVEL_EXTENSION);
+        });
+
+        ui.separator();
+        let level_arena = &mut editor.level.arena;
+        ui.add(egui::Slider::new(&mut level_arena.length, LEVEL_MIN_ARENA_LENGTH..=LEVEL_MAX_ARENA_LENGTH).text("Length (w)"));
+        ui.add(egui::Slider::new(&mut level_arena.width, LEVEL_MIN_ARENA_WIDTH..=LEVEL_MAX_ARENA_WIDTH).text("Width"));
+        ui.add(egui::Slider::new(&mut level_arena.goal_width, LEVEL_MIN_GOAL_WIDTH..=level_arena.width).text("Goal Width"));
+        ui.add(egui::Slider::new(&mut level_arena.paddle_size, LEVEL_MIN_PADDLE_SIZE..=level_arena.width / PADDLE_WIDTH).text("Paddle Size"));
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Add Box").clicked() {
+                add_obstacle(editor, ObstacleShape::Box { half_extents: Vec4::splat(EDITOR_NEW_OBSTACLE_SIZE) });
+            }
+            if ui.button("Add 3-Sphere").clicked() {
+                add_obstacle(editor, ObstacleShape::Sphere { radius: EDITOR_NEW_OBSTACLE_SIZE });
+            }
+        });
+        let mut selections: Vec<EditorSelection> = Player::HEAD_TO_HEAD.iter().map(|player| EditorSelection::Spawn(*player)).collect();
+        selections.extend((0..editor.level.obstacles.len()).map(EditorSelection::Obstacle));
+        for selection in selections {
+            let text = match selection {
+                EditorSelection::Spawn(player) => egui::RichText::new(selection.to_string()).color(get_player_color32(player)),
+                EditorSelection::Obstacle(_) => egui::RichText::new(selection.to_string()),
+            };
+            if ui.selectable_label(editor.selection == Some(selection), text).clicked() {
+                editor.selection = Some(selection);
+            }
+        }
+
+        ui.separator();
+        let half_length = editor.level.arena.length / 2.;
+        let half_width = editor.level.arena.width / 2.;
+        match editor.selection {
+            Some(selection) => {
+                ui.label(format!("Selected: {}", selection));
+                let mut position = get_selection_position(&editor.level, selection).unwrap_or(Vec4::ZERO);
+                let speed = if editor.is_snapping { editor.snap_step } else { EDITOR_DRAG_SPEED };
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut position.x).speed(speed).clamp_range(-half_width..=half_width).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut position.y).speed(speed).clamp_range(-half_width..=half_width).prefix("y: "));
+                    ui.add(egui::DragValue::new(&mut position.z).speed(speed).clamp_range(-half_width..=half_width).prefix("z: "));
+                });
+                // Spawns always sit on their goal line.
+                let is_spawn = matches!(selection, EditorSelection::Spawn(_));
+                ui.add_enabled(!is_spawn, egui::Slider::new(&mut position.w, -half_length..=half_length).text("w"));
+                set_selection_position(&mut editor.level, selection, position);
+
+                match selection {
+                    EditorSelection::Obstacle(index) => {
+                        match &mut editor.level.obstacles[index].shape {
+                            ObstacleShape::Box { half_extents } => {
+                                ui.label("Half Extents");
+                                ui.horizontal(|ui| {
+                                    for (axis, label) in ["x: ", "y: ", "z: ", "w: "].iter().enumerate() {
+                                        ui.add(egui::DragValue::new(&mut half_extents[axis]).speed(speed).clamp_range(EDITOR_DRAG_SPEED..=half_length).prefix(*label));
+                                    }
+                                });
+                            },
+                            ObstacleShape::Sphere { radius } => {
+                                ui.add(egui::DragValue::new(radius).speed(speed).clamp_range(EDITOR_DRAG_SPEED..=half_width).prefix("Radius: "));
+                            },
+                        }
+                        if ui.button("Delete").clicked() {
+                            delete_selection(editor);
+                        }
+                    },
+                    EditorSelection::Spawn(_) => {
+                        // Do nothing
+                    }
+                }
+            },
+            None => {
+                ui.add(egui::Slider::new(&mut editor.placement_w, -half_length..=half_length).text("New Obstacle w"));
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if toggle_button(ui, "Snap", editor.is_snapping) {
+                editor.is_snapping = !editor.is_snapping;
+            }
+            ui.add_enabled(editor.is_snapping, egui::DragValue::new(&mut editor.snap_step).speed(EDITOR_DRAG_SPEED).clamp_range(EDITOR_DRAG_SPEED..=1.0));
+        });
+        ui.horizontal(|ui| {
+            if ui.add_enabled(editor.history.can_undo(), egui::Button::new("Undo")).clicked() {
+                editor.undo();
+            }
+            if ui.add_enabled(editor.history.can_redo(), egui::Button::new("Redo")).clicked() {
+                editor.redo();
+            }
+        });
+
+        ui.separator();
+        match &validation {
+            Ok(()) => ui.colored_label(egui::Color32::GREEN, "Valid"),
+            Err(error) => ui.colored_label(egui::Color32::RED, error.to_string()),
+        };
+        ui.horizontal(|ui| {
+            if ui.add_enabled(validation.is_ok(), egui::Button::new("Save")).clicked() {
+                editor.status = save_level(editor, &mut levels, &asset_server);
+            }
+            if ui.add_enabled(validation.is_ok(), egui::Button::new("Test Run")).clicked() {
+                editor.level.apply(&mut arena, &mut obstacle_layout, &base_match_rules, &mut match_rules, &mut brick_grid, &mut force_fields);
+                editor.is_test_running = true;
+                commands.insert_resource(NextState(PongState::InGame));
+            }
+            if ui.button("Main Menu").clicked() {
+                commands.insert_resource(NextState(PongState::Menu));
+            }
+        });
+        ui.label(editor.status.as_str());
+    });
+
+    // Dragging in a panel moves the selection along w horizontally and along the panel's other axis vertically.
+    let (xw_image, yw_image, zw_image) = projection_images.unpack();
+    let panel_responses = [
+        instantiate_projection_panel(&mut egui_ctx, xw_image, "xw-projection", "X-W Projection", egui::Align2::LEFT_BOTTOM),
+        instantiate_projection_panel(&mut egui_ctx, yw_image, "yw-projection", "Y-W Projection", egui::Align2::CENTER_BOTTOM),
+        instantiate_projection_panel(&mut egui_ctx, zw_image, "zw-projection", "Z-W Projection", egui::Align2::RIGHT_BOTTOM),
+    ];
+    for (axis, response) in panel_responses.iter().enumerate() {
+        if response.drag_started() {
+            editor.begin_drag();
+        }
+        if response.dragged() && editor.is_dragging() {
+            let delta = response.drag_delta() * EDITOR_PANEL_UNITS_PER_PIXEL;
+            let mut offset = Vec4::new(0., 0., 0., delta.x);
+            offset[axis] = -delta.y;
+            editor.drag_by(offset);
+        }
+        if response.drag_released() {
+            editor.end_drag();
+        }
+    }
+
+    if !editor.is_dragging() {
+        editor.history.commit(&editor.level);
+    }
+}
+
+fn end_test_run_system(
+    mut editor: ResMut<EditorState>,
+) {
+    // Leaving a test run from the game over screen goes straight to the menu.
+    if editor.is_test_running {
+        editor.is_test_running = false;
+    }
+}
+
+fn test_run_ui_system(
+    mut commands: Commands,
+    mut egui_ctx: ResMut<EguiContext>,
+    editor: Res<EditorState>,
+) {
+    if !editor.is_test_running {
+        return;
+    }
+    egui::Area::new("test-run")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., PAUSE_SCREEN_SPACING))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            if ui.button("Back to Editor").clicked() {
+                commands.insert_resource(NextState(PongState::Editor));
+            }
+        });
+}
+
+// End Systems
+
+// Helper Functions
+
+pub fn get_selection_position(level: &Level, selection: EditorSelection) -> Option<Vec4> {
+    match selection {
+        EditorSelection::Obstacle(index) => level.obstacles.get(index).map(|obstacle| obstacle.center),
+        EditorSelection::Spawn(player) => Some(level.arena.get_paddle_starting_position(player)),
+    }
+}
+
+pub fn set_selection_position(level: &mut Level, selection: EditorSelection, position: Vec4) {
+    match selection {
+        EditorSelection::Obstacle(index) => {
+            match level.obstacles.get_mut(index) {
+                Some(obstacle) => obstacle.center = position,
+                None => {
+                    // Do nothing
+                }
+            }
+        },
+        EditorSelection::Spawn(player) => {
+            level.arena.paddle_spawns.insert(player, position.truncate());
+        },
+    }
+}
+
+// Finds the closest thing under the cursor in the main view, which only shows x, y and z.
+pub fn pick_selection(level: &Level, origin: Vec3, direction: Vec3) -> Option<EditorSelection> {
+    let paddle_radius = PADDLE_WIDTH * level.arena.paddle_size / 2.;
+    let mut candidates: Vec<(EditorSelection, Vec3, f32)> = Player::HEAD_TO_HEAD.iter()
+        .map(|player| (EditorSelection::Spawn(*player), level.arena.get_paddle_starting_position(*player).truncate(), paddle_radius))
+        .collect();
+    for (index, obstacle) in level.obstacles.iter().enumerate() {
+        candidates.push((EditorSelection::Obstacle(index), obstacle.center.truncate(), obstacle.shape.get_half_extents().truncate().max_element()));
+    }
+
+    candidates.into_iter()
+        .filter_map(|(selection, center, radius)| {
+            let distance_along_ray = (center - origin).dot(direction);
+            let distance_from_ray = (center - (origin + direction * distance_along_ray)).length();
+            if distance_along_ray >= 0. && distance_from_ray <= radius {
+                Some((selection, distance_along_ray))
+            } else {
+                None
+            }
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(selection, _)| selection)
+}
+
+pub fn snap(position: Vec4, step: f32) -> Vec4 {
+    if step <= 0. {
+        return position;
+    }
+    (position / step).round() * step
+}
+
+fn add_obstacle(editor: &mut EditorState, shape: ObstacleShape) {
+    editor.level.obstacles.push(Obstacle { center: Vec4::new(0., 0., 0., editor.placement_w), shape });
+    editor.selection = Some(EditorSelection::Obstacle(editor.level.obstacles.len() - 1));
+}
+
+fn delete_selection(editor: &mut EditorState) {
+    match editor.selection {
+        Some(EditorSelection::Obstacle(index)) if index < editor.level.obstacles.len() => {
+            editor.level.obstacles.remove(index);
+            editor.selection = None;
+        },
+        _ => {
+            // Spawns can't be deleted.
+        }
+    }
+}
+
+// Edits to a shipped level start out under a new name so that Save doesn't overwrite it.
+fn get_editable_file_name(file_name: String) -> String {
+    if SHIPPED_LEVELS.contains(&file_name.as_str()) {
+        format!("{}{}", file_name, EDITOR_COPY_SUFFIX)
+    } else {
+        file_name
+    }
+}
+
+fn check_file_name(file_name: &str) -> Result<(), String> {
+    if file_name.is_empty() || !file_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("File names can only use letters, numbers, - and _".to_string());
+    }
+    if SHIPPED_LEVELS.contains(&file_name) {
+        return Err(format!("{} comes with the game, so save it under another name", file_name));
+    }
+    Ok(())
+}
+
+fn save_level(editor: &EditorState, levels: &mut Levels, asset_server: &AssetServer) -> String {
+    if let Err(message) = check_file_name(&editor.file_name) {
+        return message;
+    }
+    let file_name = format!("{}{}", editor.file_name, LEVEL_EXTENSION);
+    let file_path = format!("{}/{}", LEVELS_DIRECTORY, file_name);
+    match editor.level.save(&file_path) {
+        Ok(()) => {
+            // Pick up the new contents and make it the level the menu plays.
+            let asset_path = format!("{}/{}", LEVELS_PATH, file_name);
+            asset_server.reload_asset(asset_path.as_str());
+            let handle: Handle<Level> = asset_server.load(asset_path.as_str());
+            if !levels.handles.contains(&handle) {
+                levels.handles.push(handle.clone());
+            }
+            levels.selected = handle;
+            format!("Saved to {}", file_path)
+        },
+        Err(error) => error.to_string(),
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_editor {
+    use super::*;
+
+    #[test]
+    fn test_edit_history() {
+        let mut level = Level::default();
+        let mut history = EditHistory::new(&level);
+        assert!(!history.can_undo());
+
+        history.commit(&level);
+        assert!(!history.can_undo());
+
+        level.name = "First".to_string();
+        history.commit(&level);
+        level.name = "Second".to_string();
+        history.commit(&level);
+
+        assert_eq!(history.undo().unwrap().name, "First");
+        assert_eq!(history.undo().unwrap().name, "Untitled");
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo().unwrap().name, "First");
+
+        // A new edit drops whatever could have been redone.
+        level.name = "Third".to_string();
+        history.commit(&level);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo().unwrap().name, "First");
+    }
+
+    #[test]
+    fn test_drag_is_one_undo_step() {
+        let mut editor = EditorState::default();
+        editor.placement_w = 1.;
+        add_obstacle(&mut editor, ObstacleShape::Sphere { radius: 0.2 });
+        editor.history.commit(&editor.level);
+
+        editor.begin_drag();
+        editor.drag_by(Vec4::new(0.26, 0., 0., 0.));
+        editor.drag_by(Vec4::new(0.26, 0., 0., 0.));
+        editor.end_drag();
+        assert_eq!(editor.level.obstacles[0].center, Vec4::new(0.5, 0., 0., 1.));
+
+        editor.undo();
+        assert_eq!(editor.level.obstacles[0].center, Vec4::new(0., 0., 0., 1.));
+        editor.undo();
+        assert!(editor.level.obstacles.is_empty());
+        assert_eq!(editor.selection, None);
+    }
+
+    #[test]
+    fn test_snap() {
+        assert!(snap(Vec4::new(0.26, -0.14, 0.05, 1.01), 0.1).distance(Vec4::new(0.3, -0.1, 0.1, 1.0)) < 0.0001);
+        assert_eq!(snap(Vec4::new(0.26, -0.14, 0.05, 1.01), 0.), Vec4::new(0.26, -0.14, 0.05, 1.01));
+    }
+
+    #[test]
+    fn test_pick_selection() {
+        let mut level = Level::default();
+        level.obstacles.push(Obstacle { center: Vec4::new(0.5, 0., 0., 1.), shape: ObstacleShape::Sphere { radius: 0.2 } });
+        level.obstacles.push(Obstacle { center: Vec4::new(0.5, 1., 0., -1.), shape: ObstacleShape::Sphere { radius: 0.2 } });
+        let origin = Vec3::new(0.5, -3., 0.);
+
+        assert_eq!(pick_selection(&level, origin, Vec3::Y), Some(EditorSelection::Obstacle(0)));
+        assert_eq!(pick_selection(&level, origin, -Vec3::Y), None);
+        let blue_spawn = level.arena.get_paddle_starting_position(Player::Blue).truncate();
+        assert_eq!(pick_selection(&level, blue_spawn - Vec3::Y, Vec3::Y), Some(EditorSelection::Spawn(Player::Blue)));
+    }
+
+    #[test]
+    fn test_shipped_levels_are_not_overwritten() {
+        assert_eq!(get_editable_file_name("default".to_string()), "default-copy");
+        assert_eq!(get_editable_file_name("my-level".to_string()), "my-level");
+        assert!(check_file_name("narrow-goals").is_err());
+        assert!(check_file_name("narrow goals").is_err());
+        assert!(check_file_name("").is_err());
+        assert_eq!(check_file_name("narrow-goals-copy"), Ok(()));
+    }
+}
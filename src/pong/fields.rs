@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::constants::*;
+use crate::pong::player::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Attractor {
+    pub position: Vec4,
+    // Negative to push the ball away instead.
+    pub strength: f32,
+}
+
+// Resources
+
+// Forces on the ball while it's in play.  With none of them set the ball flies in straight lines as usual.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForceFields {
+    pub gravity: Vec4,
+    pub drag: f32,
+    pub attractors: Vec<Attractor>,
+}
+
+impl ForceFields {
+
+    pub fn is_empty(&self) -> bool {
+        self.gravity == Vec4::ZERO && self.drag == 0. && self.attractors.is_empty()
+    }
+
+    pub fn get_acceleration(&self, position: Vec4, velocity: Vec4) -> Vec4 {
+        let mut acceleration = self.gravity - self.drag * velocity;
+        for attractor in self.attractors.iter() {
+            let offset = attractor.position - position;
+            // Softened so that passing right by the centre doesn't fling the ball off at a huge speed.
+            let distance_squared = offset.length_squared() + FORCE_FIELD_SOFTENING * FORCE_FIELD_SOFTENING;
+            acceleration += attractor.strength * offset / (distance_squared * distance_squared.sqrt());
+        }
+        acceleration
+    }
+
+    // The same fields as seen by the given player, for working in their frame.
+    pub fn to_goal_frame(&self, player: Player) -> Self {
+        Self {
+            gravity: player.to_goal_frame(self.gravity),
+            drag: self.drag,
+            attractors: self.attractors.iter()
+                .map(|attractor| Attractor { position: player.to_goal_frame(attractor.position), ..*attractor })
+                .collect(),
+        }
+    }
+}
+
+// End Resources
+
+// Helper Functions
+
+// Semi-implicit Euler, so orbits round an attractor don't spiral outwards the way they would with plain Euler.  The frame
+// is split into equal substeps no longer than FORCE_FIELD_STEP, which keeps a slow frame from taking one big step, though
+// the path still shifts a little with the frame rate.
+pub fn integrate(position: Vec4, velocity: Vec4, delta_seconds: f32, fields: &ForceFields) -> (Vec4, Vec4) {
+    if fields.is_empty() {
+        return (position + velocity * delta_seconds, velocity);
+    }
+    let steps = (delta_seconds / FORCE_FIELD_STEP).ceil().max(1.);
+    let step = delta_seconds / steps;
+    let mut position = position;
+    let mut velocity = velocity;
+    for _ in 0..steps as usize {
+        velocity += fields.get_acceleration(position, velocity) * step;
+        position += velocity * step;
+    }
+    (position, velocity)
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_fields {
+    use super::*;
+
+    #[test]
+    fn test_no_fields_is_a_straight_line() {
+        let fields = ForceFields::default();
+        assert!(fields.is_empty());
+        let velocity = Vec4::new(0.1, -0.2, 0.3, 1.);
+        assert_eq!(integrate(Vec4::ZERO, velocity, 0.5, &fields), (velocity * 0.5, velocity));
+    }
+
+    #[test]
+    fn test_gravity() {
+        let fields = ForceFields { gravity: -Vec4::Y, ..Default::default() };
+        let (position, velocity) = integrate(Vec4::ZERO, Vec4::W, 1., &fields);
+        assert!((velocity.y + 1.).abs() < 0.0001);
+        // Close to the exact -t^2 / 2, give or take one step's worth.
+        assert!((position.y + 0.5).abs() < 2. * FORCE_FIELD_STEP);
+        assert!((position.w - 1.).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_drag_slows_the_ball() {
+        let fields = ForceFields { drag: 0.5, ..Default::default() };
+        let (_, velocity) = integrate(Vec4::ZERO, Vec4::W, 1., &fields);
+        assert!(velocity.w < 1. && velocity.w > 0.);
+        assert!((velocity.w - (-0.5_f32).exp()).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_attractors() {
+        let attractor = Attractor { position: Vec4::X, strength: 1. };
+        let fields = ForceFields { attractors: vec![attractor], ..Default::default() };
+        assert!(fields.get_acceleration(Vec4::ZERO, Vec4::ZERO).x > 0.);
+
+        let repulsor = Attractor { strength: -1., ..attractor };
+        let fields = ForceFields { attractors: vec![repulsor], ..Default::default() };
+        assert!(fields.get_acceleration(Vec4::ZERO, Vec4::ZERO).x < 0.);
+    }
+
+    #[test]
+    fn test_to_goal_frame() {
+        let fields = ForceFields {
+            gravity: Vec4::X,
+            drag: 0.1,
+            attractors: vec![Attractor { position: Vec4::new(0.5, 0., 0., 1.), strength: 1. }],
+        };
+        assert_eq!(fields.to_goal_frame(Player::Blue), fields);
+        let framed = fields.to_goal_frame(Player::Green);
+        assert_eq!(framed.gravity, Vec4::W);
+        assert_eq!(framed.attractors[0].position, Vec4::new(1., 0., 0., 0.5));
+    }
+}
@@ -0,0 +1,429 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::asset::{AssetLoader, LoadContext, LoadState, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::breakout::BrickGrid;
+use crate::pong::components::*;
+use crate::pong::config::{parse_ron, save_ron, RonError};
+use crate::pong::fields::ForceFields;
+use crate::pong::constants::*;
+use crate::pong::obstacles::{Obstacle, ObstacleLayout};
+use crate::pong::player::Player;
+use crate::pong::resources::*;
+use crate::pong::rules::{BaseMatchRules, MatchRules};
+
+pub const LEVELS_PATH: &str = "levels";
+pub const DEFAULT_LEVEL_PATH: &str = "levels/default.level.ron";
+pub const LEVEL_EXTENSION: &str = ".level.ron";
+// Where the asset server finds LEVELS_PATH when run from the repository.
+pub const LEVELS_DIRECTORY: &str = "assets/levels";
+// The levels that come with the game, which the editor won't save over.
+pub const SHIPPED_LEVELS: [&str; 3] = ["default", "gravity-well", "narrow-goals"];
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<Level>()
+            .add_asset_loader(LevelLoader)
+            .add_startup_system(level_load_system)
+            .add_enter_system(PongState::Menu, level_reapply_system)
+            .add_system(level_apply_system)
+            .add_system(arena_scale_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleOverrides {
+    pub points_to_win: Option<usize>,
+    pub win_by_two: Option<bool>,
+    pub sets_to_win: Option<usize>,
+    pub serves_per_turn: Option<usize>,
+}
+
+impl RuleOverrides {
+
+    pub fn apply(&self, rules: &MatchRules) -> MatchRules {
+        MatchRules {
+            points_to_win: self.points_to_win.unwrap_or(rules.points_to_win),
+            win_by_two: self.win_by_two.unwrap_or(rules.win_by_two),
+            sets_to_win: self.sets_to_win.unwrap_or(rules.sets_to_win),
+            serves_per_turn: self.serves_per_turn.unwrap_or(rules.serves_per_turn),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TypeUuid)]
+#[uuid = "5b0e3c1a-8f4d-4e62-9c7a-2d6b1f9e8a43"]
+#[serde(default)]
+pub struct Level {
+    pub name: String,
+    pub arena: Arena,
+    pub obstacles: Vec<Obstacle>,
+    pub rules: RuleOverrides,
+    // Only used in Breakout.
+    pub bricks: BrickGrid,
+    pub fields: ForceFields,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            name: "Untitled".to_string(),
+            arena: Arena::default(),
+            obstacles: Vec::new(),
+            rules: RuleOverrides::default(),
+            bricks: BrickGrid::default(),
+            fields: ForceFields::default(),
+        }
+    }
+}
+
+impl Level {
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LevelError> {
+        let level: Level = parse_ron(bytes).map_err(LevelError::Ron)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), LevelError> {
+        self.validate()?;
+        save_ron(self, path).map_err(LevelError::Ron)
+    }
+
+    pub fn validate(&self) -> Result<(), LevelError> {
+        let arena = &self.arena;
+        check_range("arena.length", arena.length, LEVEL_MIN_ARENA_LENGTH, LEVEL_MAX_ARENA_LENGTH)?;
+        check_range("arena.width", arena.width, LEVEL_MIN_ARENA_WIDTH, LEVEL_MAX_ARENA_WIDTH)?;
+        check_range("arena.goal_width", arena.goal_width, LEVEL_MIN_GOAL_WIDTH, arena.width)?;
+        // The paddle has to fit inside the arena.
+        check_range("arena.paddle_size", arena.paddle_size, LEVEL_MIN_PADDLE_SIZE, arena.width / PADDLE_WIDTH)?;
+        for (player, spawn) in arena.paddle_spawns.iter() {
+            if spawn.abs().max_element() > arena.width / 2. {
+                return Err(LevelError::SpawnOutsideArena { player: *player, position: *spawn });
+            }
+        }
+
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            let half_extents = obstacle.shape.get_half_extents();
+            if half_extents.min_element() <= 0. {
+                return Err(LevelError::EmptyObstacle { index });
+            }
+            let far_corner = obstacle.center.abs() + half_extents;
+            if far_corner.truncate().max_element() > arena.width / 2. || far_corner.w > arena.length / 2. {
+                return Err(LevelError::ObstacleOutsideArena { index, center: obstacle.center });
+            }
+        }
+
+        let bricks = &self.bricks;
+        for count in bricks.cells {
+            check_range("bricks.cells", count as f32, 1., BREAKOUT_MAX_CELLS as f32)?;
+        }
+        check_range("bricks.w_range.x", bricks.w_range.x, -arena.length / 2., arena.length / 2.)?;
+        check_range("bricks.w_range.y", bricks.w_range.y, bricks.w_range.x, arena.length / 2.)?;
+        // The gap can't swallow a whole brick.
+        check_range("bricks.gap", bricks.gap, 0., bricks.get_cell_size(arena).min_element())?;
+        for (index, hole) in bricks.holes.iter().enumerate() {
+            if hole.iter().zip(bricks.cells.iter()).any(|(position, count)| position >= count) {
+                return Err(LevelError::BrickHoleOutsideGrid { index });
+            }
+        }
+
+        let fields = &self.fields;
+        check_range("fields.gravity", fields.gravity.length(), 0., FORCE_FIELD_MAX_GRAVITY)?;
+        check_range("fields.drag", fields.drag, 0., FORCE_FIELD_MAX_DRAG)?;
+        for (index, attractor) in fields.attractors.iter().enumerate() {
+            check_range("fields.attractors.strength", attractor.strength, -FORCE_FIELD_MAX_STRENGTH, FORCE_FIELD_MAX_STRENGTH)?;
+            if attractor.position.truncate().abs().max_element() > arena.width / 2. || attractor.position.w.abs() > arena.length / 2. {
+                return Err(LevelError::AttractorOutsideArena { index, position: attractor.position });
+            }
+        }
+
+        let rules = &self.rules;
+        check_optional_rule("rules.points_to_win", rules.points_to_win, MATCH_MAX_POINTS_TO_WIN)?;
+        check_optional_rule("rules.sets_to_win", rules.sets_to_win, MATCH_MAX_SETS_TO_WIN)?;
+        check_optional_rule("rules.serves_per_turn", rules.serves_per_turn, MATCH_MAX_SERVES_PER_TURN)?;
+        Ok(())
+    }
+
+    pub fn apply(&self, arena: &mut Arena, obstacle_layout: &mut ObstacleLayout, base_match_rules: &BaseMatchRules, match_rules: &mut MatchRules, brick_grid: &mut BrickGrid, force_fields: &mut ForceFields) {
+        *arena = self.arena.clone();
+        *obstacle_layout = ObstacleLayout(self.obstacles.clone());
+        *match_rules = self.rules.apply(&base_match_rules.0);
+        *brick_grid = self.bricks.clone();
+        *force_fields = self.fields.clone();
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Ron(RonError),
+    OutOfRange { field: &'static str, value: f32, min: f32, max: f32 },
+    SpawnOutsideArena { player: Player, position: Vec3 },
+    EmptyObstacle { index: usize },
+    ObstacleOutsideArena { index: usize, center: Vec4 },
+    BrickHoleOutsideGrid { index: usize },
+    AttractorOutsideArena { index: usize, position: Vec4 },
+}
+
+impl Display for LevelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelError::Ron(error) => write!(f, "{}", error),
+            LevelError::OutOfRange { field, value, min, max } => write!(f, "{} is {} but must be between {} and {}", field, value, min, max),
+            LevelError::SpawnOutsideArena { player, position } => write!(f, "{}'s paddle spawn {} is outside the arena", player, position),
+            LevelError::EmptyObstacle { index } => write!(f, "obstacles[{}] has no size", index),
+            LevelError::ObstacleOutsideArena { index, center } => write!(f, "obstacles[{}] at {} sticks out of the arena", index, center),
+            LevelError::BrickHoleOutsideGrid { index } => write!(f, "bricks.holes[{}] is outside the brick grid", index),
+            LevelError::AttractorOutsideArena { index, position } => write!(f, "fields.attractors[{}] at {} is outside the arena", index, position),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+// Resources
+
+pub struct Levels {
+    pub handles: Vec<Handle<Level>>,
+    pub selected: Handle<Level>,
+}
+
+// End Resources
+
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let level = Level::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+// Systems
+
+fn level_load_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let selected = asset_server.load(DEFAULT_LEVEL_PATH);
+    let handles = match asset_server.load_folder(LEVELS_PATH) {
+        Ok(handles) => handles.into_iter().map(|handle| handle.typed()).collect(),
+        Err(error) => {
+            println!("Failed to load the levels folder: {:?}", error);
+            vec![selected.clone()]
+        }
+    };
+    commands.insert_resource(Levels { handles, selected });
+}
+
+fn level_apply_system(
+    mut asset_events: EventReader<AssetEvent<Level>>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    mut arena: ResMut<Arena>,
+    mut obstacle_layout: ResMut<ObstacleLayout>,
+    base_match_rules: Res<BaseMatchRules>,
+    mut match_rules: ResMut<MatchRules>,
+    mut brick_grid: ResMut<BrickGrid>,
+    mut force_fields: ResMut<ForceFields>,
+) {
+    let is_selected_level_updated = asset_events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == levels.selected,
+        AssetEvent::Removed { .. } => false,
+    });
+    if !is_selected_level_updated && !levels.is_changed() && !base_match_rules.is_changed() {
+        return;
+    }
+    match level_assets.get(&levels.selected) {
+        Some(level) => {
+            level.apply(&mut arena, &mut obstacle_layout, &base_match_rules, &mut match_rules, &mut brick_grid, &mut force_fields);
+        },
+        None => {
+            // Still loading, or it failed to load, in which case the asset server has logged why.
+        }
+    }
+}
+
+fn level_reapply_system(
+    mut levels: ResMut<Levels>,
+) {
+    // Puts back anything the editor changed, and picks up levels it saved.
+    levels.set_changed();
+}
+
+fn arena_scale_system(
+    arena: Res<Arena>,
+    mut arena_query: Query<&mut Transform, With<ArenaComponent>>,
+    mut rectangle_query: Query<&mut Transform, (With<ArenaRectangleComponent>, Without<ArenaComponent>)>,
+) {
+    // The meshes are modelled at the default size.
+    let width_scale = arena.width / ARENA_WIDTH;
+    for mut transform in arena_query.iter_mut() {
+        transform.scale = Vec3::ONE * width_scale;
+    }
+    // In the projection panels w runs along x.
+    for mut transform in rectangle_query.iter_mut() {
+        transform.scale = Vec3::new(arena.length / ARENA_LENGTH, width_scale, width_scale);
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+pub fn get_level_label(level_assets: &Assets<Level>, asset_server: &AssetServer, handle: &Handle<Level>) -> (String, bool) {
+    match level_assets.get(handle) {
+        Some(level) => (level.name.clone(), true),
+        None => {
+            let path = asset_server.get_handle_path(handle)
+                .map_or("Unknown level".to_string(), |path| path.path().display().to_string());
+            match asset_server.get_load_state(handle) {
+                LoadState::Failed => (format!("{} (invalid, see the log)", path), false),
+                _ => (format!("{} (loading)", path), false),
+            }
+        }
+    }
+}
+
+fn check_range(field: &'static str, value: f32, min: f32, max: f32) -> Result<(), LevelError> {
+    // Written this way round so that NaN fails too.
+    if value >= min && value <= max {
+        Ok(())
+    } else {
+        Err(LevelError::OutOfRange { field, value, min, max })
+    }
+}
+
+fn check_optional_rule(field: &'static str, value: Option<usize>, max: usize) -> Result<(), LevelError> {
+    match value {
+        Some(value) => check_range(field, value as f32, 1., max as f32),
+        None => Ok(()),
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_level {
+    use super::*;
+    use crate::pong::obstacles::ObstacleShape;
+    use crate::pong::fields::Attractor;
+
+    #[test]
+    fn test_shipped_levels_are_valid() {
+        let default_level = Level::from_bytes(include_bytes!("../../assets/levels/default.level.ron")).unwrap();
+        assert_eq!(default_level.arena, Arena::default());
+        assert_eq!(ObstacleLayout(default_level.obstacles), ObstacleLayout::default());
+        assert_eq!(default_level.rules, RuleOverrides::default());
+
+        let narrow_goals = Level::from_bytes(include_bytes!("../../assets/levels/narrow-goals.level.ron")).unwrap();
+        assert!(narrow_goals.arena.goal_width < narrow_goals.arena.width);
+
+        let gravity_well = Level::from_bytes(include_bytes!("../../assets/levels/gravity-well.level.ron")).unwrap();
+        assert!(!gravity_well.fields.is_empty());
+
+        for name in SHIPPED_LEVELS {
+            assert!(std::path::Path::new(LEVELS_DIRECTORY).join(format!("{}{}", name, LEVEL_EXTENSION)).exists(), "{} is not shipped", name);
+        }
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let level = Level::from_bytes(b"(name: \"Empty\")").unwrap();
+        assert_eq!(level.name, "Empty");
+        assert_eq!(level.arena, Arena::default());
+        assert!(level.obstacles.is_empty());
+        assert_eq!(level.bricks, BrickGrid::default());
+        assert!(level.fields.is_empty());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        assert!(matches!(Level::from_bytes(b"(name: 3)"), Err(LevelError::Ron(RonError::Parse(_)))));
+
+        let mut level = Level::default();
+        level.arena.goal_width = level.arena.width + 1.;
+        assert!(matches!(level.validate(), Err(LevelError::OutOfRange { field: "arena.goal_width", .. })));
+
+        let mut level = Level::default();
+        level.arena.length = -1.;
+        assert!(matches!(level.validate(), Err(LevelError::OutOfRange { field: "arena.length", .. })));
+
+        let mut level = Level::default();
+        level.obstacles.push(Obstacle { center: Vec4::new(0., 0., 0., ARENA_LENGTH / 2.), shape: ObstacleShape::Sphere { radius: 0.2 } });
+        assert!(matches!(level.validate(), Err(LevelError::ObstacleOutsideArena { index: 0, .. })));
+
+        let mut level = Level::default();
+        level.bricks.holes.push([0, 0, 0, level.bricks.cells[3]]);
+        assert!(matches!(level.validate(), Err(LevelError::BrickHoleOutsideGrid { index: 0 })));
+
+        let mut level = Level::default();
+        level.bricks.w_range = Vec2::new(2.0, 1.0);
+        assert!(matches!(level.validate(), Err(LevelError::OutOfRange { field: "bricks.w_range.y", .. })));
+
+        let mut level = Level::default();
+        level.fields.drag = -1.;
+        assert!(matches!(level.validate(), Err(LevelError::OutOfRange { field: "fields.drag", .. })));
+
+        let mut level = Level::default();
+        level.fields.attractors.push(Attractor { position: Vec4::new(0., 0., 0., ARENA_LENGTH), strength: 0.5 });
+        assert!(matches!(level.validate(), Err(LevelError::AttractorOutsideArena { index: 0, .. })));
+
+        let mut level = Level::default();
+        level.rules.sets_to_win = Some(0);
+        assert!(matches!(level.validate(), Err(LevelError::OutOfRange { field: "rules.sets_to_win", .. })));
+
+        let mut level = Level::default();
+        level.rules.serves_per_turn = Some(MATCH_MAX_SERVES_PER_TURN + 1);
+        assert!(matches!(level.validate(), Err(LevelError::OutOfRange { field: "rules.serves_per_turn", .. })));
+    }
+
+    #[test]
+    fn test_rule_overrides() {
+        let rules = MatchRules::default();
+        assert_eq!(RuleOverrides::default().apply(&rules), rules);
+        let overrides = RuleOverrides { points_to_win: Some(5), win_by_two: Some(false), ..Default::default() };
+        let overridden = overrides.apply(&rules);
+        assert_eq!(overridden.points_to_win, 5);
+        assert!(!overridden.win_by_two);
+        assert_eq!(overridden.sets_to_win, rules.sets_to_win);
+    }
+
+    #[test]
+    fn test_level_overrides_do_not_carry_over() {
+        let base_match_rules = BaseMatchRules(MatchRules::default());
+        let mut arena = Arena::default();
+        let mut obstacle_layout = ObstacleLayout::default();
+        let mut match_rules = MatchRules::default();
+        let mut brick_grid = BrickGrid::default();
+        let mut force_fields = ForceFields::default();
+
+        let mut narrow_goals = Level::default();
+        narrow_goals.rules.points_to_win = Some(7);
+        narrow_goals.apply(&mut arena, &mut obstacle_layout, &base_match_rules, &mut match_rules, &mut brick_grid, &mut force_fields);
+        assert_eq!(match_rules.points_to_win, 7);
+
+        Level::default().apply(&mut arena, &mut obstacle_layout, &base_match_rules, &mut match_rules, &mut brick_grid, &mut force_fields);
+        assert_eq!(match_rules, base_match_rules.0);
+    }
+}
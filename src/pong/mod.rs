@@ -6,4 +6,22 @@ pub mod components;
 pub mod resources;
 pub mod constants;
 pub mod axis;
-pub mod rotations;
\ No newline at end of file
+pub mod rotations;
+pub mod perception;
+pub mod actions;
+pub mod config;
+pub mod controls;
+pub mod rules;
+pub mod powerups;
+pub mod obstacles;
+pub mod level;
+pub mod editor;
+pub mod topology;
+pub mod squash;
+pub mod breakout;
+pub mod fields;
+pub mod campaign;
+pub mod spacetime;
+pub mod relativity;
+pub mod trajectory;
+pub mod colormap;
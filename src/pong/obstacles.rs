@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::axis::Axis;
+use crate::pong::assets::{add_w_coloured_material, despawn_object_and_projections, spawn_object_and_projections};
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::resources::*;
+
+pub struct ObstaclesPlugin;
+
+impl Plugin for ObstaclesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ObstaclesEnabled::default())
+            .insert_resource(ObstacleLayout::default())
+            .add_startup_system(obstacle_mesh_load_system)
+            .add_exit_system(PongState::Menu, spawn_obstacles_system)
+            .add_system(obstacle_layout_sync_system.run_in_state(PongState::Editor))
+            .add_system(obstacle_collision_system.run_in_state(PongState::InGame));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ObstacleShape {
+    // An axis-aligned 4D box, i.e. a hyperrectangle.
+    Box { half_extents: Vec4 },
+    // A 3-sphere: every point at the given 4D distance from the centre.
+    Sphere { radius: f32 },
+}
+
+impl ObstacleShape {
+
+    pub fn get_half_extents(&self) -> Vec4 {
+        match self {
+            ObstacleShape::Box { half_extents } => *half_extents,
+            ObstacleShape::Sphere { radius } => Vec4::splat(*radius),
+        }
+    }
+
+    pub fn get_range(&self, center: Vec4, axis: Axis) -> (f32, f32) {
+        let index = axis.index();
+        let half_extent = self.get_half_extents()[index];
+        (center[index] - half_extent, center[index] + half_extent)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub center: Vec4,
+    pub shape: ObstacleShape,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct ObstacleComponent(pub ObstacleShape);
+
+// Resources
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObstacleLayout(pub Vec<Obstacle>);
+
+impl Default for ObstacleLayout {
+    fn default() -> Self {
+        Self(vec![
+            Obstacle {
+                center: Vec4::ZERO,
+                shape: ObstacleShape::Box { half_extents: Vec4::new(0.2, 0.2, 0.2, 0.4) },
+            },
+            Obstacle {
+                center: Vec4::new(0.5, -0.5, 0.3, 1.0),
+                shape: ObstacleShape::Box { half_extents: Vec4::splat(0.15) },
+            },
+            Obstacle {
+                center: Vec4::new(-0.5, 0.5, -0.3, -1.0),
+                shape: ObstacleShape::Sphere { radius: 0.2 },
+            },
+        ])
+    }
+}
+
+struct ObstacleMeshes {
+    cube: Handle<Mesh>,
+    sphere: Handle<Mesh>,
+}
+
+// End Resources
+
+// Systems
+
+fn obstacle_mesh_load_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    // Unit-sized so that render_system can stretch them to each obstacle's extents.
+    commands.insert_resource(ObstacleMeshes {
+        cube: meshes.add(Mesh::from(shape::Cube { size: 2. })),
+        sphere: meshes.add(Mesh::from(shape::UVSphere { radius: 1., ..default() })),
+    });
+}
+
+fn spawn_obstacles_system(
+    mut commands: Commands,
+    obstacles_enabled: Res<ObstaclesEnabled>,
+    obstacle_layout: Res<ObstacleLayout>,
+    obstacle_meshes: Res<ObstacleMeshes>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    obstacle_query: Query<Entity, With<ObstacleComponent>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    let obstacles: &[Obstacle] = if obstacles_enabled.0 { &obstacle_layout.0 } else { &[] };
+    respawn_obstacles(&mut commands, obstacles, &obstacle_meshes, &mut materials, &obstacle_query, &projection_query);
+}
+
+fn obstacle_layout_sync_system(
+    mut commands: Commands,
+    obstacle_layout: Res<ObstacleLayout>,
+    obstacle_meshes: Res<ObstacleMeshes>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    obstacle_query: Query<Entity, With<ObstacleComponent>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    // The editor always shows the obstacles it's working on, whatever the menu says.
+    if !obstacle_layout.is_changed() {
+        return;
+    }
+    respawn_obstacles(&mut commands, &obstacle_layout.0, &obstacle_meshes, &mut materials, &obstacle_query, &projection_query);
+}
+
+fn obstacle_collision_system(
+    serve: Res<Serve>,
+    mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent), With<BallComponent>>,
+    obstacle_query: Query<(&PositionComponent, &ObstacleComponent), Without<BallComponent>>,
+) {
+    if !serve.is_in_play() {
+        // The ball is parked for the serve and shouldn't be knocked off its mark.
+        return;
+    }
+    for (mut ball_position, mut ball_velocity) in ball_query.iter_mut() {
+        for (obstacle_position, obstacle_component) in obstacle_query.iter() {
+            match get_obstacle_contact(obstacle_component.0, obstacle_position.0, ball_position.0, BALL_RADIUS) {
+                Some((normal, penetration)) => {
+                    // Push the ball back out so it can't get stuck inside.
+                    ball_position.0 += normal * penetration;
+                    ball_velocity.0 = reflect_off_surface(ball_velocity.0, normal);
+                },
+                None => {
+                    // Do nothing
+                }
+            }
+        }
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+fn respawn_obstacles(
+    commands: &mut Commands,
+    obstacles: &[Obstacle],
+    obstacle_meshes: &ObstacleMeshes,
+    materials: &mut Assets<StandardMaterial>,
+    obstacle_query: &Query<Entity, With<ObstacleComponent>>,
+    projection_query: &Query<(Entity, &ProjectionComponent)>,
+) {
+    for entity in obstacle_query.iter() {
+        despawn_object_and_projections(commands, entity, projection_query);
+    }
+    for obstacle in obstacles.iter() {
+        let mesh = match obstacle.shape {
+            ObstacleShape::Box { .. } => &obstacle_meshes.cube,
+            ObstacleShape::Sphere { .. } => &obstacle_meshes.sphere,
+        };
+        // See-through so the ball stays visible behind it.
+        let material = add_w_coloured_material(materials, AlphaMode::Blend);
+        spawn_object_and_projections(
+            commands,
+            mesh,
+            &material,
+            obstacle.center,
+            None,
+            ObstacleComponent(obstacle.shape),
+            None::<AIComponent>,
+        );
+    }
+}
+
+// Returns the outward surface normal at the contact and how far the ball has sunk in.
+pub fn get_obstacle_contact(shape: ObstacleShape, center: Vec4, ball_position: Vec4, ball_radius: f32) -> Option<(Vec4, f32)> {
+    let offset = ball_position - center;
+    match shape {
+        ObstacleShape::Box { half_extents } => {
+            let closest_point = offset.clamp(-half_extents, half_extents);
+            let outside_offset = offset - closest_point;
+            let distance = outside_offset.length();
+            if distance >= ball_radius {
+                return None;
+            }
+            if distance > 0. {
+                return Some((outside_offset / distance, ball_radius - distance));
+            }
+            // The centre is inside the box, so leave through whichever face is closest.
+            let depths = half_extents - offset.abs();
+            let mut axis = 0;
+            for index in 1..4 {
+                if depths[index] < depths[axis] {
+                    axis = index;
+                }
+            }
+            let mut normal = Vec4::ZERO;
+            normal[axis] = if offset[axis] < 0. { -1. } else { 1. };
+            Some((normal, depths[axis] + ball_radius))
+        },
+        ObstacleShape::Sphere { radius } => {
+            let distance = offset.length();
+            if distance >= radius + ball_radius {
+                return None;
+            }
+            let normal = if distance > 0. { offset / distance } else { Vec4::W };
+            Some((normal, radius + ball_radius - distance))
+        },
+    }
+}
+
+pub fn reflect_off_surface(velocity: Vec4, normal: Vec4) -> Vec4 {
+    let normal_speed = velocity.dot(normal);
+    if normal_speed >= 0. {
+        // Already moving away from the surface.
+        return velocity;
+    }
+    velocity - 2. * normal_speed * normal
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_obstacles {
+    use super::*;
+
+    #[test]
+    fn test_box_contact() {
+        let shape = ObstacleShape::Box { half_extents: Vec4::new(0.2, 0.2, 0.2, 0.4) };
+        assert_eq!(get_obstacle_contact(shape, Vec4::ZERO, Vec4::new(0.3, 0., 0., 0.), 0.05), None);
+
+        let (normal, penetration) = get_obstacle_contact(shape, Vec4::ZERO, Vec4::new(0.22, 0., 0., 0.), 0.05).unwrap();
+        assert_eq!(normal, Vec4::X);
+        assert!((penetration - 0.03).abs() < 0.0001);
+
+        let (normal, _) = get_obstacle_contact(shape, Vec4::ZERO, Vec4::new(0., 0., 0., -0.42), 0.05).unwrap();
+        assert_eq!(normal, -Vec4::W);
+
+        // Centre inside: leave through the nearest face.
+        let (normal, penetration) = get_obstacle_contact(shape, Vec4::ZERO, Vec4::new(0., -0.15, 0., 0.), 0.05).unwrap();
+        assert_eq!(normal, -Vec4::Y);
+        assert!((penetration - 0.1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_sphere_contact() {
+        let shape = ObstacleShape::Sphere { radius: 0.2 };
+        let center = Vec4::new(0., 0., 0., 1.);
+        assert_eq!(get_obstacle_contact(shape, center, Vec4::new(0.3, 0., 0., 1.), 0.05), None);
+        let (normal, penetration) = get_obstacle_contact(shape, center, Vec4::new(0., 0., 0., 0.8), 0.05).unwrap();
+        assert_eq!(normal, -Vec4::W);
+        assert!((penetration - 0.05).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_reflect_off_surface() {
+        assert_eq!(reflect_off_surface(Vec4::new(1., 0.5, 0., 1.), -Vec4::W), Vec4::new(1., 0.5, 0., -1.));
+        assert_eq!(reflect_off_surface(Vec4::new(1., 0.5, 0., -1.), -Vec4::W), Vec4::new(1., 0.5, 0., -1.));
+        let diagonal = Vec4::new(1., 1., 0., 0.).normalize();
+        let reflected = reflect_off_surface(-diagonal, diagonal);
+        assert!(reflected.distance(diagonal) < 0.0001);
+    }
+}
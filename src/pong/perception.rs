@@ -0,0 +1,133 @@
+use rand::Rng;
+
+use bevy::prelude::*;
+
+use crate::pong::colormap::ColorMap;
+use crate::pong::constants::*;
+use crate::pong::pong::{get_color_from_w, get_w_from_color};
+
+// What a human actually gets to see of an object: the main 3D view with w encoded as colour,
+// and the three 2D projection panels, each of which pairs one of x, y, z with w.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observation {
+    pub main_view: Vec3,
+    pub main_view_color: Color,
+    pub xw_projection: Vec2,
+    pub yw_projection: Vec2,
+    pub zw_projection: Vec2,
+}
+
+#[derive(Component, Clone, Copy, Default)]
+pub struct PerceptionComponent {
+    last_estimate: Option<Vec4>,
+    velocity_estimate: Vec4,
+}
+
+impl PerceptionComponent {
+
+    pub fn update(&mut self, estimate: Vec4, delta_seconds: f32) -> (Vec4, Vec4) {
+        match self.last_estimate {
+            Some(last_estimate) if delta_seconds > 0. => {
+                if (estimate - last_estimate).length() > PERCEPTION_RESET_DISTANCE {
+                    // The ball was teleported (e.g. after a goal), so the old velocity tells us nothing.
+                    self.velocity_estimate = Vec4::ZERO;
+                } else {
+                    let measured_velocity = (estimate - last_estimate) / delta_seconds;
+                    self.velocity_estimate = self.velocity_estimate.lerp(measured_velocity, PERCEPTION_VELOCITY_SMOOTHING);
+                }
+            },
+            _ => {
+                // Nothing to difference against yet.
+            }
+        }
+        self.last_estimate = Some(estimate);
+        (estimate, self.velocity_estimate)
+    }
+}
+
+pub fn observe(position: Vec4, arena_length: f32, color_map: ColorMap, rng: &mut impl Rng) -> Observation {
+    let main_view = Vec3::new(
+        add_noise(position.x, PERCEPTION_MAIN_VIEW_NOISE, rng),
+        add_noise(position.y, PERCEPTION_MAIN_VIEW_NOISE, rng),
+        add_noise(position.z, PERCEPTION_MAIN_VIEW_NOISE, rng),
+    );
+
+    let [r, g, b, _] = get_color_from_w(position.w, arena_length, color_map).as_rgba_f32();
+    let mut perceive_channel = |channel: f32| quantise(add_noise(channel, PERCEPTION_COLOR_NOISE, rng), PERCEPTION_COLOR_STEP).clamp(0., 1.);
+    let main_view_color = Color::rgb(perceive_channel(r), perceive_channel(g), perceive_channel(b));
+
+    Observation {
+        main_view,
+        main_view_color,
+        xw_projection: observe_projection(position.x, position.w, rng),
+        yw_projection: observe_projection(position.y, position.w, rng),
+        zw_projection: observe_projection(position.z, position.w, rng),
+    }
+}
+
+pub fn estimate_position(observation: &Observation, arena_length: f32, color_map: ColorMap) -> Vec4 {
+    let w_from_color = get_w_from_color(observation.main_view_color, arena_length, color_map);
+    Vec4::new(
+        (observation.main_view.x + observation.xw_projection.x) / 2.,
+        (observation.main_view.y + observation.yw_projection.x) / 2.,
+        (observation.main_view.z + observation.zw_projection.x) / 2.,
+        (w_from_color + observation.xw_projection.y + observation.yw_projection.y + observation.zw_projection.y) / 4.,
+    )
+}
+
+fn observe_projection(coordinate: f32, w: f32, rng: &mut impl Rng) -> Vec2 {
+    Vec2::new(
+        quantise(add_noise(coordinate, PERCEPTION_PROJECTION_NOISE, rng), PERCEPTION_PIXEL_SIZE),
+        quantise(add_noise(w, PERCEPTION_PROJECTION_NOISE, rng), PERCEPTION_PIXEL_SIZE),
+    )
+}
+
+fn add_noise(value: f32, amount: f32, rng: &mut impl Rng) -> f32 {
+    if amount <= 0. {
+        return value;
+    }
+    value + rng.gen_range(-amount..amount)
+}
+
+fn quantise(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
+
+#[cfg(test)]
+mod test_perception {
+    use super::*;
+
+    #[test]
+    fn test_quantise() {
+        assert_eq!(quantise(0.26, 0.5), 0.5);
+        assert_eq!(quantise(0.24, 0.5), 0.0);
+        assert_eq!(quantise(-0.76, 0.5), -1.0);
+    }
+
+    #[test]
+    fn test_estimate_position_is_close() {
+        let rng = &mut rand::thread_rng();
+        let position = Vec4::new(0.3, -0.4, 0.5, 1.2);
+        for color_map in ColorMap::ALL {
+            for _ in 0..100 {
+                let estimate = estimate_position(&observe(position, ARENA_LENGTH, color_map, rng), ARENA_LENGTH, color_map);
+                assert!(estimate.truncate().distance(position.truncate()) < 0.2, "Estimate {} too far from {}", estimate, position);
+                assert!((estimate.w - position.w).abs() < 0.2, "Estimate {} too far from {} with {}", estimate, position, color_map);
+            }
+        }
+    }
+
+    #[test]
+    fn test_perception_component_estimates_velocity() {
+        let mut perception = PerceptionComponent::default();
+        let velocity = Vec4::new(0.1, 0.2, 0.3, 1.0);
+        let mut estimated_velocity = Vec4::ZERO;
+        for i in 0..100 {
+            estimated_velocity = perception.update(velocity * (i as f32) * 0.01, 0.01).1;
+        }
+        assert!(estimated_velocity.distance(velocity) < 0.0001);
+
+        let (_, estimated_velocity) = perception.update(Vec4::ZERO, 0.01);
+        assert_eq!(estimated_velocity, Vec4::ZERO);
+    }
+}
@@ -1,5 +1,8 @@
 use std::fmt::{Display, Formatter};
-use bevy::prelude::Vec3;
+use bevy::prelude::{Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::pong::axis::Axis;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Target {
@@ -13,12 +16,69 @@ impl Target {
             position,
         }
     }
+
+    // Picks the corner of the cube [-half_extent, half_extent]^3 that is farthest from the given point.
+    pub fn farthest_corner_from(position: Vec3, half_extent: f32) -> Self {
+        let corner = Vec3::new(
+            -position.x.signum(),
+            -position.y.signum(),
+            -position.z.signum(),
+        ) * half_extent;
+        Self::new(corner)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Player {
     Blue,
-    Red
+    Red,
+    Green,
+    Yellow,
+}
+
+impl Player {
+
+    pub const ALL: [Player; 4] = [Player::Blue, Player::Red, Player::Green, Player::Yellow];
+    // The players in a normal match, and the only ones with controls.  Green and Yellow only join in four-player mode.
+    pub const HEAD_TO_HEAD: [Player; 2] = [Player::Blue, Player::Red];
+
+    // The player defending the opposite goal.
+    pub fn opponent(&self) -> Player {
+        match self {
+            Player::Blue => Player::Red,
+            Player::Red => Player::Blue,
+            Player::Green => Player::Yellow,
+            Player::Yellow => Player::Green,
+        }
+    }
+
+    pub fn get_goal_axis(&self) -> Axis {
+        match self {
+            Player::Blue | Player::Red => Axis::W,
+            Player::Green | Player::Yellow => Axis::X,
+        }
+    }
+
+    // Which end of the goal axis the player defends.
+    pub fn get_goal_side(&self) -> f32 {
+        match self {
+            Player::Blue | Player::Green => -1.,
+            Player::Red | Player::Yellow => 1.,
+        }
+    }
+
+    // Swaps the player's goal axis with w, so that every paddle can be handled as if it defended a w goal.
+    // Swapping is its own inverse, so this also takes vectors back out of the player's frame.
+    pub fn to_goal_frame(&self, vector: Vec4) -> Vec4 {
+        match self.get_goal_axis() {
+            Axis::X => Vec4::new(vector.w, vector.y, vector.z, vector.x),
+            _ => vector,
+        }
+    }
 }
 
 impl Display for Player {
@@ -26,6 +86,35 @@ impl Display for Player {
         match self {
             Player::Blue => write!(f, "Blue"),
             Player::Red => write!(f, "Red"),
+            Player::Green => write!(f, "Green"),
+            Player::Yellow => write!(f, "Yellow"),
         }
     }
 }
+
+#[cfg(test)]
+mod test_player {
+    use super::*;
+
+    #[test]
+    fn test_farthest_corner_from() {
+        assert_eq!(Target::farthest_corner_from(Vec3::new(0.5, -0.5, 0.1), 1.0).position(), Vec3::new(-1., 1., -1.));
+        assert_eq!(Target::farthest_corner_from(Vec3::new(-0.2, 0.3, -0.9), 2.0).position(), Vec3::new(2., -2., 2.));
+    }
+
+    #[test]
+    fn test_opponent() {
+        assert_eq!(Player::Blue.opponent(), Player::Red);
+        assert_eq!(Player::Red.opponent(), Player::Blue);
+        assert_eq!(Player::Green.opponent(), Player::Yellow);
+        assert_eq!(Player::Yellow.opponent(), Player::Green);
+    }
+
+    #[test]
+    fn test_to_goal_frame() {
+        let vector = Vec4::new(1., 2., 3., 4.);
+        assert_eq!(Player::Blue.to_goal_frame(vector), vector);
+        assert_eq!(Player::Green.to_goal_frame(vector), Vec4::new(4., 2., 3., 1.));
+        assert_eq!(Player::Yellow.to_goal_frame(Player::Yellow.to_goal_frame(vector)), vector);
+    }
+}
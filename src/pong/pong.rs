@@ -1,13 +1,23 @@
-use rand::{seq::SliceRandom, Rng};
-
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
+use crate::pong::actions::{Action, PlayerAction};
 use crate::pong::components::*;
 use crate::pong::resources::*;
 use crate::pong::constants::*;
-use crate::pong::player::Player;
+use crate::pong::player::{Player, Target};
 use crate::pong::axis::Axis;
+use crate::pong::controls::get_axis_input;
+use crate::pong::perception::*;
+use crate::pong::rules::{BaseMatchRules, MatchRules, MatchScore, PointOutcome};
+use crate::pong::powerups::{ActiveEffects, LastHitter, PowerUpKind};
+use crate::pong::obstacles::ObstacleComponent;
+use crate::pong::assets::{add_w_coloured_material, despawn_object_and_projections, spawn_object_and_projections};
+use crate::pong::topology::*;
+use crate::pong::breakout::BrickComponent;
+use crate::pong::fields::{integrate, ForceFields};
+use crate::pong::relativity::Relativity;
+use crate::pong::colormap::ColorMap;
 
 pub struct PongPlugin;
 
@@ -16,16 +26,45 @@ impl Plugin for PongPlugin {
         app
         .insert_resource(Time::default())
         .insert_resource(Input::<KeyCode>::default())
+        .insert_resource(AimAssist::default())
+        .insert_resource(DefenceZone::default())
+        .insert_resource(Arena::default())
+        .insert_resource(ViewMapping::default())
+        .insert_resource(ForceFields::default())
+        .insert_resource(GameMode::default())
+        .insert_resource(AIPerception::default())
+        .insert_resource(AIPerformance::default())
+        .insert_resource(BaseMatchRules::default())
+        .insert_resource(MatchRules::default())
+        .insert_resource(MatchScore::default())
+        .insert_resource(ViewMode::default())
+        .insert_resource(MatchWins::default())
+        .insert_resource(Serve::new(Player::Blue))
         .add_event::<ScoreEvent>()
-        .add_enter_system(PongState::InGame, ball_initial_velocity_system)
+        .add_event::<PaddleHitEvent>()
+        .add_enter_system(PongState::InGame, clear_paddle_velocity_system)
+        .add_exit_system(PongState::InGame, clear_paddle_velocity_system)
+        .add_system(pause_input_system.run_in_state(PongState::InGame))
+        .add_exit_system(PongState::Menu, apply_game_mode_system)
+        .add_exit_system(PongState::Menu, reset_match_system)
+        .add_exit_system(PongState::GameOver, reset_match_system)
+        .add_exit_system(PongState::Editor, reset_match_system)
         .add_system(input_system.run_in_state(PongState::InGame))
+        .add_system(serve_system.run_in_state(PongState::InGame))
+        .add_system(serve_marker_system)
         .add_system(movement_system.run_in_state(PongState::InGame))
+        .add_system(add_perception_system)
+        .add_system(add_prediction_system)
         .add_system(ai_system.run_in_state(PongState::InGame))
+        .add_system(ai_performance_system.run_in_state(PongState::InGame))
         .add_system(collision_system.run_in_state(PongState::InGame))
         .add_system(projection_system.run_in_state(PongState::InGame))
+        .add_system(projection_system.run_in_state(PongState::Editor))
         .add_system(render_system.run_in_state(PongState::InGame))
         .add_system(render_system.run_in_state(PongState::Paused))
-        .add_system(score_system.run_in_state(PongState::InGame));
+        .add_system(render_system.run_in_state(PongState::Editor))
+        .add_system(score_system.run_in_state(PongState::InGame))
+        .add_system(score_display_system);
     }
 }
 
@@ -42,151 +81,484 @@ impl Plugin for PongPlugin {
 // Events
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct ScoreEvent(Player);
+pub struct ScoreEvent {
+    pub scorer: Player,
+    pub conceder: Player,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaddleHitEvent(pub Player);
 
 // End Events
 
 // Systems
 
-fn ball_initial_velocity_system(
-    mut ball_query: Query<&mut VelocityComponent, With<BallComponent>>,
-) {
-    for mut velocity in ball_query.iter_mut() {
-        velocity.0 = roll_initial_velocity();
-    }
-}
-
-fn input_system(
+fn apply_game_mode_system(
     mut commands: Commands,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut paddle_query: Query<&mut VelocityComponent, With<PlayerInputComponent>>,
+    game_mode: Res<GameMode>,
+    mut arena: ResMut<Arena>,
+    mut topology: ResMut<Topology>,
+    mut view_mapping: ResMut<ViewMapping>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+    paddle_query: Query<(Entity, &PaddleComponent, &Handle<Mesh>, Option<&PlayerInputComponent>, Option<&AIComponent>)>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
-        commands.insert_resource(NextState(PongState::Paused));
-    }
-    for mut velocity in paddle_query.iter_mut() {
-        if keyboard_input.just_pressed(KeyCode::W) {
-            velocity.0 += PADDLE_SPEED*Vec4::new(0., 0., 1., 0.);
-        }
-        if keyboard_input.just_pressed(KeyCode::S) {
-            velocity.0 += PADDLE_SPEED*Vec4::new(0., 0., -1., 0.);
-        }
-        if keyboard_input.just_pressed(KeyCode::A) {
-            velocity.0 += PADDLE_SPEED*Vec4::new(-1., 0., 0., 0.);
+    let players = game_mode.get_players();
+    match *game_mode {
+        GameMode::FourPlayer => {
+            // Every goal has to be the same distance from the middle, and the x walls are goals now so they can't wrap.
+            arena.length = arena.width;
+            topology.x = WallBehaviour::Reflect;
+            // x and w both hold goals, so show both of them in 3D and colour by z instead.
+            *view_mapping = ViewMapping { color_axis: Axis::Z };
+        },
+        GameMode::Spacetime => {
+            // w is time, shown in 3D so that world-lines can be drawn along it, and it has to fit the view like x and y.
+            arena.length = arena.width;
+            *view_mapping = ViewMapping { color_axis: Axis::Z };
+        },
+        _ => {
+            *view_mapping = ViewMapping::default();
         }
-        if keyboard_input.just_pressed(KeyCode::D) {
-            velocity.0 += PADDLE_SPEED*Vec4::new(1., 0., 0., 0.);
-        }
-        if keyboard_input.just_pressed(KeyCode::Up) {
-            velocity.0 += PADDLE_SPEED*Vec4::new(0., 1., 0., 0.);
+    }
+
+    let mut maybe_paddle_mesh = None;
+    let mut existing_players = Vec::new();
+    for (entity, paddle_component, mesh, maybe_player_input, maybe_ai) in paddle_query.iter() {
+        maybe_paddle_mesh = Some(mesh.clone());
+        existing_players.push(paddle_component.0);
+        if !players.contains(&paddle_component.0) {
+            despawn_object_and_projections(&mut commands, entity, &projection_query);
+            continue;
         }
-        if keyboard_input.just_pressed(KeyCode::Down) {
-            velocity.0 += PADDLE_SPEED*Vec4::new(0., -1., 0., 0.);
+        let is_human = match (*game_mode, paddle_component.0) {
+            (_, Player::Blue) => true,
+            (GameMode::HotSeat, Player::Red) => true,
+            _ => false,
+        };
+        if is_human {
+            commands.entity(entity).remove::<AIComponent>();
+            if maybe_player_input.is_none() {
+                commands.entity(entity).insert(PlayerInputComponent(ControlScheme::Keyboard));
+            }
+        } else {
+            commands.entity(entity).remove::<PlayerInputComponent>();
+            if maybe_ai.is_none() {
+                commands.entity(entity).insert(AIComponent(AI_PADDLE_SPEED_MEDIUM));
+            }
         }
+    }
 
-        
-        if keyboard_input.just_released(KeyCode::W) {
-            velocity.0 -= PADDLE_SPEED*Vec4::new(0., 0., 1., 0.);
-        }
-        if keyboard_input.just_released(KeyCode::S) {
-            velocity.0 -= PADDLE_SPEED*Vec4::new(0., 0., -1., 0.);
+    match maybe_paddle_mesh {
+        Some(paddle_mesh) => {
+            for player in players.iter().filter(|player| !existing_players.contains(player)) {
+                let material = add_w_coloured_material(&mut materials, AlphaMode::Opaque);
+                spawn_object_and_projections(
+                    &mut commands,
+                    &paddle_mesh,
+                    &material,
+                    arena.get_paddle_starting_position(*player),
+                    Some(arena.paddle_size),
+                    PaddleComponent(*player),
+                    Some(AIComponent(AI_PADDLE_SPEED_MEDIUM)),
+                );
+            }
+        },
+        None => {
+            // Do nothing
         }
-        if keyboard_input.just_released(KeyCode::A) {
-            velocity.0 -= PADDLE_SPEED*Vec4::new(-1., 0., 0., 0.);
+    }
+}
+
+fn reset_match_system(
+    arena: Res<Arena>,
+    game_mode: Res<GameMode>,
+    match_rules: Res<MatchRules>,
+    mut match_score: ResMut<MatchScore>,
+    mut serve: ResMut<Serve>,
+    mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent), With<BallComponent>>,
+    mut paddle_query: Query<(&mut PositionComponent, &PaddleComponent), Without<BallComponent>>,
+) {
+    // The loser of the last match serves first in the rematch.
+    let first_server = match_score.winner().map_or(Player::Blue, |winner| winner.opponent());
+    *match_score = MatchScore::new(first_server).with_players(game_mode.get_players());
+    *serve = Serve::new(match_score.server(&match_rules));
+    for (mut ball_position, mut ball_velocity) in ball_query.iter_mut() {
+        ball_position.0 = Vec4::ZERO;
+        ball_velocity.0 = Vec4::ZERO;
+    }
+    for (mut paddle_position, paddle_component) in paddle_query.iter_mut() {
+        paddle_position.0 = arena.get_paddle_starting_position(paddle_component.0);
+    }
+}
+
+fn serve_system(
+    time: Res<Time>,
+    arena: Res<Arena>,
+    action_input: Res<Input<PlayerAction>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut serve: ResMut<Serve>,
+    mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent), With<BallComponent>>,
+    paddle_query: Query<(&PositionComponent, &VelocityComponent, &PaddleComponent, Option<&PlayerInputComponent>), Without<BallComponent>>,
+) {
+    let paddle_positions: Vec<(Player, Vec4)> = paddle_query.iter()
+        .map(|(position, _, paddle_component, _)| (paddle_component.0, position.0))
+        .collect();
+    let server = serve.server;
+    let (server_position, server_velocity, maybe_player_input) = match paddle_query.iter().find(|(_, _, paddle_component, _)| paddle_component.0 == serve.server) {
+        Some((position, velocity, _, maybe_player_input)) => (position.0, velocity.0, maybe_player_input.copied()),
+        None => return,
+    };
+
+    serve.phase = match serve.phase {
+        ServePhase::InPlay => return,
+        ServePhase::Countdown(seconds_left) if seconds_left > time.delta_seconds() => ServePhase::Countdown(seconds_left - time.delta_seconds()),
+        ServePhase::Countdown(_) | ServePhase::Ready => ServePhase::Ready,
+    };
+
+    // Work in the server's frame, where they defend a w goal.
+    let goal_line = arena.get_goal_line(server);
+    let ball_position = server.to_goal_frame(server_position) - goal_line.signum() * SERVE_BALL_OFFSET * Vec4::W;
+    let transverse_velocity = match maybe_player_input {
+        Some(_) => server.to_goal_frame(server_velocity).truncate() / PADDLE_SPEED * SERVE_TRANSVERSE_SPEED,
+        None => {
+            // The AI serves into the corner farthest from its opponent.
+            let target = get_target(&paddle_positions, server, &arena);
+            get_required_transverse_velocity(ball_position, 1., target, &arena)
         }
-        if keyboard_input.just_released(KeyCode::D) {
-            velocity.0 -= PADDLE_SPEED*Vec4::new(1., 0., 0., 0.);
+    };
+    let ball_position = server.to_goal_frame(ball_position);
+    serve.launch_velocity = server.to_goal_frame(transverse_velocity.clamp_length_max(BALL_MAX_TRANSVERSE_SPEED).extend(-goal_line.signum()));
+
+    let is_launched = serve.phase == ServePhase::Ready && match maybe_player_input {
+        Some(PlayerInputComponent(ControlScheme::Mouse)) => {
+            mouse_input.just_pressed(MouseButton::Left) || action_input.just_pressed(PlayerAction(serve.server, Action::Serve))
+        },
+        Some(_) => action_input.just_pressed(PlayerAction(serve.server, Action::Serve)),
+        None => true,
+    };
+    for (mut position, mut velocity) in ball_query.iter_mut() {
+        position.0 = ball_position;
+        velocity.0 = if is_launched { serve.launch_velocity } else { Vec4::ZERO };
+    }
+    if is_launched {
+        serve.phase = ServePhase::InPlay;
+    }
+}
+
+fn serve_marker_system(
+    serve: Res<Serve>,
+    ball_query: Query<&PositionComponent, With<BallComponent>>,
+    mut marker_query: Query<(Entity, &mut PositionComponent), (With<ServeMarkerComponent>, Without<BallComponent>)>,
+    mut visibility_query: Query<(Entity, &mut Visibility, Option<&ProjectionComponent>)>,
+) {
+    let is_visible = !serve.is_in_play();
+    let mut marker_entities: Vec<Entity> = Vec::new();
+    for (entity, mut marker_position) in marker_query.iter_mut() {
+        marker_entities.push(entity);
+        match ball_query.get_single() {
+            Ok(ball_position) => {
+                marker_position.0 = ball_position.0 + serve.launch_velocity.normalize_or_zero() * SERVE_MARKER_DISTANCE;
+            },
+            Err(_) => {
+                // Do nothing
+            }
         }
-        if keyboard_input.just_released(KeyCode::Up) {
-            velocity.0 -= PADDLE_SPEED*Vec4::new(0., 1., 0., 0.);
+    }
+    // The marker's projections are separate entities, so hide those too.
+    for (entity, mut visibility, maybe_projection) in visibility_query.iter_mut() {
+        let is_marker = marker_entities.contains(&entity)
+            || maybe_projection.map_or(false, |projection| marker_entities.contains(&projection.0));
+        if is_marker && visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
         }
-        if keyboard_input.just_released(KeyCode::Down) {
-            velocity.0 -= PADDLE_SPEED*Vec4::new(0., -1., 0., 0.);
+    }
+}
+
+fn pause_input_system(
+    mut commands: Commands,
+    action_input: Res<Input<PlayerAction>>,
+) {
+    if Player::ALL.iter().any(|player| action_input.just_pressed(PlayerAction(*player, Action::Pause))) {
+        commands.insert_resource(NextState(PongState::Paused));
+    }
+}
+
+fn input_system(
+    action_input: Res<Input<PlayerAction>>,
+    defence_zone: Res<DefenceZone>,
+    active_effects: Res<ActiveEffects>,
+    mut paddle_query: Query<(&mut VelocityComponent, &PaddleComponent, &PlayerInputComponent)>,
+) {
+    for (mut velocity, paddle_component, player_input) in paddle_query.iter_mut() {
+        if player_input.0 != ControlScheme::Keyboard {
+            continue;
         }
+        // Recompute from the held actions every tick so that a missed release can't leave the paddle drifting.
+        let direction = Action::ALL.iter()
+            .filter(|action| action_input.pressed(PlayerAction(paddle_component.0, **action)))
+            .filter_map(|action| action.direction())
+            .fold(Vec4::ZERO, |sum, direction| sum + direction);
+        let control_sign = active_effects.get_control_sign(paddle_component.0);
+        velocity.0 = (control_sign*PADDLE_SPEED*direction.truncate()).extend(0.);
+        velocity.0.w = get_w_velocity(&action_input, paddle_component.0, &defence_zone);
+    }
+}
+
+fn clear_paddle_velocity_system(
+    mut paddle_query: Query<&mut VelocityComponent, With<PaddleComponent>>,
+) {
+    for mut velocity in paddle_query.iter_mut() {
+        velocity.0 = Vec4::ZERO;
     }
 }
 
 fn movement_system(
     time: Res<Time>,
-    mut ball_query: Query<(&mut PositionComponent, &VelocityComponent)>,
+    serve: Res<Serve>,
+    force_fields: Res<ForceFields>,
+    relativity: Res<Relativity>,
+    active_effects: Res<ActiveEffects>,
+    mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent, Option<&BallComponent>, Option<&PaddleComponent>)>,
 ) {
-    for (mut position, velocity) in ball_query.iter_mut() {
-        position.0 += velocity.0 * time.delta_seconds();
+    let no_fields = ForceFields::default();
+    for (mut position, mut velocity, maybe_ball, maybe_paddle) in ball_query.iter_mut() {
+        let (speed_multiplier, fields) = match maybe_ball {
+            // The ball sits still on its mark until it's served.
+            Some(_) if serve.is_in_play() => (active_effects.get_ball_speed_multiplier(), &*force_fields),
+            Some(_) => (active_effects.get_ball_speed_multiplier(), &no_fields),
+            None => (1., &no_fields),
+        };
+        match maybe_paddle {
+            Some(_) if relativity.enabled => {
+                // The controls set a paddle's proper velocity, so pushing harder only ever gets it closer to the speed of light.
+                position.0 += relativity.from_proper_velocity(velocity.0) * time.delta_seconds();
+            },
+            _ => {
+                (position.0, velocity.0) = integrate(position.0, relativity.limit_speed(velocity.0), speed_multiplier * time.delta_seconds(), fields);
+            }
+        }
+    }
+}
+
+fn add_perception_system(
+    mut commands: Commands,
+    ai_query: Query<Entity, (With<AIComponent>, Without<PerceptionComponent>)>,
+) {
+    for entity in ai_query.iter() {
+        commands.entity(entity).insert(PerceptionComponent::default());
+    }
+}
+
+fn add_prediction_system(
+    mut commands: Commands,
+    ai_query: Query<Entity, (With<AIComponent>, Without<PredictionComponent>)>,
+) {
+    for entity in ai_query.iter() {
+        commands.entity(entity).insert(PredictionComponent::default());
     }
 }
 
 fn ai_system(
-    mut ball_query: Query<(&PositionComponent, &VelocityComponent), With<BallComponent>>,
-    mut paddle_query: Query<(&PositionComponent, &mut VelocityComponent, &AIComponent), Without<BallComponent>>,
+    time: Res<Time>,
+    arena: Res<Arena>,
+    topology: Res<Topology>,
+    force_fields: Res<ForceFields>,
+    ai_perception: Res<AIPerception>,
+    color_map: Res<ColorMap>,
+    active_effects: Res<ActiveEffects>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+    mut paddle_hit_event_reader: EventReader<PaddleHitEvent>,
+    ball_query: Query<(&PositionComponent, &VelocityComponent), With<BallComponent>>,
+    opponent_query: Query<(&PositionComponent, &PaddleComponent), Without<BallComponent>>,
+    mut paddle_query: Query<(&PositionComponent, &mut VelocityComponent, &AIComponent, &PaddleComponent, &ScaleComponent, Option<&mut PerceptionComponent>, Option<&mut PredictionComponent>), Without<BallComponent>>,
 ) {
-    for (ball_position, _ball_velocity) in ball_query.iter_mut() {
-        for (paddle_position, mut paddle_velocity, ai_component) in paddle_query.iter_mut() {
+    let rng = &mut rand::thread_rng();
+    // Read both so neither is left holding old events.
+    let is_new_shot = score_event_reader.iter().count() + paddle_hit_event_reader.iter().count() > 0;
+    let paddle_positions: Vec<(Player, Vec4)> = opponent_query.iter()
+        .map(|(position, paddle_component)| (paddle_component.0, position.0))
+        .collect();
+    for (true_ball_position, true_ball_velocity) in ball_query.iter() {
+        for (paddle_position, mut paddle_velocity, ai_component, paddle_component, scale_component, maybe_perception, maybe_prediction) in paddle_query.iter_mut() {
+            let player = paddle_component.0;
+            let (ball_position, ball_velocity) = match (*ai_perception, maybe_perception) {
+                (AIPerception::ProjectionsOnly, Some(mut perception)) => {
+                    let estimate = estimate_position(&observe(true_ball_position.0, arena.length, *color_map, rng), arena.length, *color_map);
+                    perception.update(estimate, time.delta_seconds())
+                },
+                _ => (true_ball_position.0, true_ball_velocity.0),
+            };
+            // Work in the paddle's frame, where it defends a w goal.
+            let ball_position = player.to_goal_frame(ball_position);
+            let ball_velocity = player.to_goal_frame(ball_velocity);
+            let framed_paddle_position = player.to_goal_frame(paddle_position.0);
             let paddle_speed = ai_component.0;
-            let mut direction = (ball_position.0 - paddle_position.0).truncate();
-            direction = direction.normalize();
-            paddle_velocity.0 = (direction * paddle_speed).extend(0.);
+            let paddle_radius = (PADDLE_WIDTH * scale_component.0)/2.;
+            let fields = force_fields.to_goal_frame(player);
+            let arrival = match maybe_prediction {
+                Some(mut prediction) if !fields.is_empty() => {
+                    match prediction.age {
+                        // Refreshed now and then as well as on each hit, to catch bounces off obstacles and a
+                        // perception estimate that is still settling.
+                        Some(age) if !is_new_shot && age < FORCE_FIELD_PREDICTION_REFRESH_SECONDS => {
+                            prediction.age = Some(age + time.delta_seconds());
+                        },
+                        _ => {
+                            prediction.arrival = predict_ball_arrival(ball_position, ball_velocity, framed_paddle_position.w, &arena, &topology, &fields);
+                            prediction.age = Some(0.);
+                        }
+                    }
+                    prediction.arrival
+                },
+                _ => predict_ball_arrival(ball_position, ball_velocity, framed_paddle_position.w, &arena, &topology, &fields),
+            };
+            let desired_position = match arrival {
+                Some((arrival_position, arrival_velocity)) => {
+                    let target = get_target(&paddle_positions, player, &arena);
+                    get_aiming_position(arrival_position, arrival_velocity, target, paddle_radius, &arena)
+                },
+                None => ball_position.truncate(),
+            };
+            // With inverted controls the AI ends up chasing the mirror image of where it wants to be.
+            let desired_position = active_effects.get_control_sign(player) * desired_position;
+            paddle_velocity.0 = player.to_goal_frame(get_velocity_towards(
+                framed_paddle_position.truncate(),
+                desired_position,
+                paddle_speed,
+                time.delta_seconds(),
+            ).extend(0.));
+        }
+    }
+}
+
+fn ai_performance_system(
+    ai_perception: Res<AIPerception>,
+    mut ai_performance: ResMut<AIPerformance>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+    mut paddle_hit_event_reader: EventReader<PaddleHitEvent>,
+    ai_query: Query<&PaddleComponent, With<AIComponent>>,
+) {
+    let ai_players: Vec<Player> = ai_query.iter().map(|paddle_component| paddle_component.0).collect();
+    for score_event in score_event_reader.iter() {
+        if ai_players.contains(&score_event.conceder) {
+            ai_performance.record_miss(*ai_perception);
+        }
+    }
+    for paddle_hit_event in paddle_hit_event_reader.iter() {
+        if ai_players.contains(&paddle_hit_event.0) {
+            ai_performance.record_return(*ai_perception);
         }
     }
 }
 
 fn collision_system(
+    arena: Res<Arena>,
+    topology: Res<Topology>,
+    game_mode: Res<GameMode>,
+    last_hitter: Res<LastHitter>,
+    aim_assist: Res<AimAssist>,
+    defence_zone: Res<DefenceZone>,
+    relativity: Res<Relativity>,
     mut ball_query: Query<(&mut PositionComponent, &mut VelocityComponent), With<BallComponent>>,
-    mut paddle_query: Query<(&mut PositionComponent, &PaddleComponent, &ScaleComponent), Without<BallComponent>>,
+    mut paddle_query: Query<(&mut PositionComponent, &VelocityComponent, &PaddleComponent, &ScaleComponent, Option<&PlayerInputComponent>), Without<BallComponent>>,
     mut score_event_writer: EventWriter<ScoreEvent>,
+    mut paddle_hit_event_writer: EventWriter<PaddleHitEvent>,
 ) {
+    let players = game_mode.get_players();
     for (mut ball_position, mut ball_velocity) in ball_query.iter_mut() {
-        match is_goal_collision(ball_position.0) {
+        match is_goal_collision(ball_position.0, &arena, players) {
             Some(player) => {
+                // Park the ball until score_system works out who serves next.
                 ball_position.0 = Vec4::new(0., 0., 0., 0.);
-                ball_velocity.0 = roll_initial_velocity();
+                ball_velocity.0 = Vec4::ZERO;
 
-                score_event_writer.send(ScoreEvent(player));
+                let conceder = player.opponent();
+                score_event_writer.send(ScoreEvent { scorer: get_scorer(conceder, last_hitter.0), conceder });
             },
             None => {
                 // Do nothing
             }
         }
-        match is_wall_collision(ball_position.0) {
+        match is_wall_collision(ball_position.0, &arena, &topology, players) {
             Some(axis) => {
-                ball_velocity.0 = reflect_on_axis_towards_zero(ball_position.0, ball_velocity.0, axis);
+                let (position, velocity) = hit_wall(ball_position.0, ball_velocity.0, axis, &arena, &topology);
+                ball_position.0 = position;
+                ball_velocity.0 = velocity;
             },
             None => {
                 // Do nothing
             }
         }
-        for (paddle_position, _paddle_component, scale_component) in paddle_query.iter() {
+        let paddle_positions: Vec<(Player, Vec4)> = paddle_query.iter()
+            .map(|(position, _, paddle_component, _, _)| (paddle_component.0, position.0))
+            .collect();
+        for (paddle_position, paddle_velocity, paddle_component, scale_component, maybe_player_input) in paddle_query.iter() {
+            // Work in the paddle's frame, where it defends a w goal.
+            let player = paddle_component.0;
+            let framed_ball_position = player.to_goal_frame(ball_position.0);
+            let framed_ball_velocity = player.to_goal_frame(ball_velocity.0);
+            let framed_paddle_position = player.to_goal_frame(paddle_position.0);
             let paddle_scalar = scale_component.0;
             if is_ball_paddle_collision(
-                ball_position.0,
-                paddle_position.0,
+                framed_ball_position,
+                framed_paddle_position,
                 paddle_scalar,
-            ) {
-                ball_velocity.0 = reflect_w(ball_velocity.0);
+            ) && is_moving_towards_w(framed_ball_velocity, framed_paddle_position.w) {
+                let paddle_radius = (PADDLE_WIDTH * paddle_scalar)/2.;
+                let mut deflected_velocity = if relativity.enabled {
+                    // Bounce off in the paddle's own frame and carry the result back, so the paddle's motion adds on relativistically.
+                    let framed_paddle_velocity = player.to_goal_frame(relativity.from_proper_velocity(paddle_velocity.0));
+                    let rest_frame_velocity = relativity.add_velocities(-framed_paddle_velocity, framed_ball_velocity);
+                    let deflected_velocity = deflect_off_paddle(framed_ball_position, rest_frame_velocity, framed_paddle_position, paddle_radius);
+                    relativity.add_velocities(framed_paddle_velocity, relativity.limit_speed(deflected_velocity))
+                } else {
+                    deflect_off_paddle(framed_ball_position, framed_ball_velocity, framed_paddle_position, paddle_radius)
+                };
+                if aim_assist.0 && maybe_player_input.is_some() {
+                    let target = get_target(&paddle_positions, player, &arena);
+                    let required_velocity = get_required_transverse_velocity(framed_ball_position, deflected_velocity.w, target, &arena);
+                    deflected_velocity = deflected_velocity.truncate()
+                        .lerp(required_velocity, AIM_ASSIST_STRENGTH)
+                        .extend(deflected_velocity.w);
+                }
+                ball_velocity.0 = player.to_goal_frame(deflected_velocity);
+                paddle_hit_event_writer.send(PaddleHitEvent(player));
             }
         }
     }
-    for (mut paddle_position, _paddle_component, scale_component) in paddle_query.iter_mut() {
+    for (mut paddle_position, _, paddle_component, scale_component, _) in paddle_query.iter_mut() {
+        let player = paddle_component.0;
         let paddle_scalar = scale_component.0;
-        let clamp_distance = ARENA_WIDTH/2. - PADDLE_WIDTH * paddle_scalar/2.;
-        paddle_position.0 = clamp_3d(
-            paddle_position.0, 
+        let clamp_distance = arena.width/2. - PADDLE_WIDTH * paddle_scalar/2.;
+        let mut framed_paddle_position = clamp_3d(
+            player.to_goal_frame(paddle_position.0),
             -clamp_distance*Vec3::ONE,
             clamp_distance*Vec3::ONE,
             CLAMP_PADDING,
         );
+        framed_paddle_position.w = clamp_to_defence_zone(framed_paddle_position.w, player, &defence_zone, &arena);
+        paddle_position.0 = player.to_goal_frame(framed_paddle_position);
     }
 }
 
 fn projection_system(
-    position_query: Query<(Entity, &PositionComponent), Without<ProjectionComponent>>,
-    mut projection_query: Query<(&mut PositionComponent, &ProjectionComponent)>,
+    position_query: Query<(Entity, &PositionComponent, Option<&ScaleComponent>), Without<ProjectionComponent>>,
+    mut projection_query: Query<(&mut PositionComponent, Option<&mut ScaleComponent>, &ProjectionComponent)>,
 ) {
-    for (mut projection_position, projection_component) in projection_query.iter_mut() {
+    for (mut projection_position, maybe_projection_scale, projection_component) in projection_query.iter_mut() {
         let (projection_entity, projection_translation, projection_rotations) = projection_component.unpack();
-        let real_entity_position = position_query.iter()
-            .filter(|(entity, _)| *entity == projection_entity)
-            .next()
-            .expect("A Projection Entity exists without a corresponding object.").1;
+        let (_, real_entity_position, maybe_real_entity_scale) = position_query.get(projection_entity)
+            .expect("A Projection Entity exists without a corresponding object.");
+        match (maybe_projection_scale, maybe_real_entity_scale) {
+            (Some(mut projection_scale), Some(real_entity_scale)) => {
+                // Keep projections in step when a paddle is resized.
+                projection_scale.0 = real_entity_scale.0;
+            },
+            _ => {
+                // Do nothing
+            }
+        }
         
         let mut rotated_position = real_entity_position.0;
         for rotation in projection_rotations.iter() {
@@ -197,24 +569,83 @@ fn projection_system(
 }
 
 fn render_system(
+    arena: Res<Arena>,
+    view_mapping: Res<ViewMapping>,
+    color_map: Res<ColorMap>,
+    relativity: Res<Relativity>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut query: Query<(&mut Transform, &mut MaterialHandleComponent, &PositionComponent, Option<&ScaleComponent>, Option<&ProjectionComponent>), With<NeedsRenderingComponent>>,
+    active_effects: Res<ActiveEffects>,
+    ball_query: Query<&PositionComponent, With<BallComponent>>,
+    velocity_query: Query<(&VelocityComponent, Option<&PaddleComponent>)>,
+    obstacle_query: Query<&ObstacleComponent>,
+    ghost_query: Query<&GhostBallComponent>,
+    paddle_query: Query<&PaddleComponent>,
+    brick_query: Query<&BrickComponent>,
+    mut query: Query<(Entity, &mut Transform, &mut MaterialHandleComponent, &PositionComponent, Option<&ScaleComponent>, Option<&ProjectionComponent>, Option<&BallComponent>), With<NeedsRenderingComponent>>,
 ) {
-    for (mut transform, material, position, maybe_scale_component, maybe_projection) in query.iter_mut() {
-        *transform = Transform::from_translation(position.0.truncate());
+    let is_ball_w_invisible = active_effects.is_active(PowerUpKind::WInvisibleBall);
+    let ball_position = ball_query.iter().next().map_or(Vec4::ZERO, |ball_position| ball_position.0);
+    let color_extent = view_mapping.get_color_extent(&arena);
+    // The main view is Blue's, so everything is squashed by how fast it's moving relative to Blue's paddle.
+    let observer_velocity = velocity_query.iter()
+        .find(|(_, maybe_paddle)| maybe_paddle.map_or(false, |paddle_component| paddle_component.0 == Player::Blue))
+        .map_or(Vec4::ZERO, |(velocity, maybe_paddle)| get_arena_velocity(velocity, maybe_paddle, &relativity));
+    for (entity, mut transform, material, position, maybe_scale_component, maybe_projection, maybe_ball) in query.iter_mut() {
         let scale_modifier = maybe_scale_component.unwrap_or(&ScaleComponent(1.)).0;
-        transform.scale = Vec3::ONE * scale_modifier;
+        let source_entity = maybe_projection.map_or(entity, |projection_component| projection_component.0);
+        let maybe_obstacle = obstacle_query.get(source_entity).ok();
+        let maybe_extents = get_extents(maybe_obstacle, brick_query.get(source_entity).ok(), paddle_query.get(source_entity).ok(), scale_modifier);
+        let contraction = velocity_query.get(source_entity).ok()
+            .map_or(Vec4::ONE, |(velocity, maybe_paddle)| {
+                relativity.get_contraction(relativity.add_velocities(-observer_velocity, get_arena_velocity(velocity, maybe_paddle, &relativity)))
+            });
         match maybe_projection {
-            Some(_) => {
+            Some(projection_component) => {
                 // Let the material get updated by the non-projected w.
-                
+                *transform = Transform::from_translation(position.0.truncate());
+
                 // For projected objects, we don't want to scale them in the w direction (which is represented in the projection as x)
-                transform.scale = Vec3::new(1., transform.scale.y, transform.scale.z);
+                transform.scale = Vec3::new(1., scale_modifier, scale_modifier);
+
+                // Obstacles and paddles have their own extents, so rotate those round to whichever axes they land on.
+                match maybe_extents {
+                    Some(mut extents) => {
+                        for rotation in projection_component.2.iter() {
+                            extents = rotation.rotate(extents);
+                        }
+                        transform.scale = extents.abs().truncate();
+                    },
+                    None => {
+                        // Do nothing
+                    }
+                }
+                let mut contraction = contraction;
+                for rotation in projection_component.2.iter() {
+                    contraction = rotation.rotate(contraction);
+                }
+                transform.scale *= contraction.abs().truncate();
             },
             None => {
+                let view_position = view_mapping.to_view(position.0);
+                *transform = Transform::from_translation(view_position.truncate());
+                transform.scale = match maybe_extents {
+                    Some(extents) => view_mapping.to_view(extents).truncate(),
+                    None => Vec3::ONE * scale_modifier,
+                } * view_mapping.to_view(contraction).truncate();
+                let is_ghost = ghost_query.contains(entity);
                 match materials.get_mut(&material.0) {
                     Some(material) => {
-                        material.base_color = get_color_from_w(position.0.w, ARENA_LENGTH);
+                        material.base_color = if is_ball_w_invisible && (maybe_ball.is_some() || is_ghost) {
+                            Color::GRAY
+                        } else {
+                            match maybe_obstacle {
+                                Some(obstacle_component) => get_obstacle_color(obstacle_component, position.0, ball_position, &view_mapping, color_extent, *color_map),
+                                None => get_color_from_w(view_position.w, color_extent, *color_map),
+                            }
+                        };
+                        if is_ghost {
+                            material.base_color.set_a(GHOST_BALL_ALPHA);
+                        }
                     },
                     None => {
                         panic!("Material not found.");
@@ -226,35 +657,54 @@ fn render_system(
 }
 
 fn score_system(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    view_mode: Res<ViewMode>,
+    match_rules: Res<MatchRules>,
+    mut match_score: ResMut<MatchScore>,
+    mut match_wins: ResMut<MatchWins>,
+    mut serve: ResMut<Serve>,
     mut score_event_reader: EventReader<ScoreEvent>,
-    mut score_query: Query<(&mut Text, &mut ScoreComponent)>,
 ) {
     for score_event in score_event_reader.iter() {
-        for (mut text, mut score_component) in score_query.iter_mut() {
-            if score_event.0 == score_component.0 {
-                score_component.1 += 1;
-                text.sections[0].value = (text.sections[0].value.parse::<u32>().unwrap() + 1).to_string();
+        if game_mode.is_solo() {
+            // Nobody to lose to, so just serve again.
+            *serve = Serve::new(score_event.conceder);
+            continue;
+        }
+        match match_score.award_point(score_event.scorer, &match_rules) {
+            PointOutcome::MatchWon(winner) => {
+                match_wins.record_win(*view_mode, winner);
+                commands.insert_resource(NextState(PongState::GameOver));
+            },
+            PointOutcome::Point | PointOutcome::SetWon(_) => {
+                *serve = Serve::new(match_score.server(&match_rules));
             }
         }
     }
 }
 
+fn score_display_system(
+    game_mode: Res<GameMode>,
+    match_score: Res<MatchScore>,
+    mut score_query: Query<(&mut Text, &mut Visibility, &mut ScoreComponent)>,
+) {
+    if !match_score.is_changed() && !game_mode.is_changed() {
+        return;
+    }
+    for (mut text, mut visibility, mut score_component) in score_query.iter_mut() {
+        score_component.1 = match_score.points(score_component.0);
+        text.sections[0].value = score_component.1.to_string();
+        // Single-player modes keep their own counts instead.
+        visibility.is_visible = !game_mode.is_solo() && game_mode.get_players().contains(&score_component.0);
+    }
+}
+
 
 // End Systems
 
 // Helper Functions
 
-fn roll_initial_velocity() -> Vec4 {
-    let rng = &mut rand::thread_rng();
-
-    let directions = vec![-1., 1.];
-    let w_velocity = directions.choose(rng).expect("Directions is never empty.");
-    let x_velocity = rng.gen_range(0.0..1.0);
-    let y_velocity = rng.gen_range(0.0..1.0);
-    let z_velocity = rng.gen_range(0.0..1.0);
-    Vec4::new(x_velocity, y_velocity, z_velocity, *w_velocity)
-}
-
 fn reflect_on_axis_towards_zero(position: Vec4, velocity: Vec4, axis: Axis) -> Vec4 {
     let reflected_velocity = reflect_on_axis(velocity, axis);
     let toward_zero = match axis {
@@ -271,6 +721,13 @@ fn reflect_on_axis_towards_zero(position: Vec4, velocity: Vec4, axis: Axis) -> V
     }
 }
 
+fn hit_wall(position: Vec4, velocity: Vec4, axis: Axis, arena: &Arena, topology: &Topology) -> (Vec4, Vec4) {
+    match topology.get(axis) {
+        WallBehaviour::Reflect => (position, reflect_on_axis_towards_zero(position, velocity, axis)),
+        behaviour => wrap_on_axis(position, velocity, axis, arena, behaviour),
+    }
+}
+
 fn reflect_on_axis_towards_3d_origin(position: Vec4, velocity: Vec4, axis: Axis) -> Vec4 {
     let reflected_velocity = reflect_on_axis(velocity, axis);
     match is_towards_origin_3d(position, reflected_velocity) {
@@ -296,18 +753,174 @@ fn reflect_w(vector: Vec4) -> Vec4 {
     reflect_on_axis(vector, Axis::W)
 }
 
-fn is_wall_collision(ball_position: Vec4) -> Option<Axis> {
-    if ball_position.x.abs() + BALL_RADIUS > ARENA_WIDTH/2. {
-        Some(Axis::X)
-    } else if ball_position.y.abs() + BALL_RADIUS > ARENA_WIDTH/2. {
-        Some(Axis::Y)
-    } else if ball_position.z.abs() + BALL_RADIUS > ARENA_WIDTH/2. {
-        Some(Axis::Z)
+pub fn get_w_velocity(action_input: &Input<PlayerAction>, player: Player, defence_zone: &DefenceZone) -> f32 {
+    if !defence_zone.enabled {
+        return 0.;
+    }
+    PADDLE_W_SPEED * get_axis_input(action_input, PlayerAction(player, Action::MoveWPos), PlayerAction(player, Action::MoveWNeg))
+}
+
+// In the player's frame, where w is their goal axis.
+fn clamp_to_defence_zone(w: f32, player: Player, defence_zone: &DefenceZone, arena: &Arena) -> f32 {
+    let goal_line_w = arena.get_goal_line(player);
+    if !defence_zone.enabled {
+        return goal_line_w;
+    }
+    // The zone runs from the goal line towards the centre, and never past it so each paddle stays on its own side.
+    let depth = defence_zone.depth.clamp(0., arena.get_max_defence_depth());
+    let zone_front_w = goal_line_w - goal_line_w.signum() * depth;
+    w.clamp(goal_line_w.min(zone_front_w), goal_line_w.max(zone_front_w))
+}
+
+fn is_moving_towards_w(velocity: Vec4, w: f32) -> bool {
+    velocity.w * w > 0.
+}
+
+fn deflect_off_paddle(ball_position: Vec4, ball_velocity: Vec4, paddle_position: Vec4, paddle_radius: f32) -> Vec4 {
+    // The further from the centre of the paddle the ball hits, the more it gets pushed in that direction.
+    let contact_offset = ((ball_position - paddle_position).truncate() / paddle_radius).clamp(-Vec3::ONE, Vec3::ONE);
+    let transverse_velocity = ball_velocity.truncate() + contact_offset * PADDLE_DEFLECTION_SPEED;
+    reflect_w(transverse_velocity.clamp_length_max(BALL_MAX_TRANSVERSE_SPEED).extend(ball_velocity.w))
+}
+
+// The target is in the hitter's frame, across the goal they're aiming at.
+fn get_target(paddle_positions: &[(Player, Vec4)], hitter: Player, arena: &Arena) -> Target {
+    let opponent = hitter.opponent();
+    let opponent_position = paddle_positions.iter()
+        .find(|(player, _)| *player == opponent)
+        .map_or(Vec3::ZERO, |(_, position)| hitter.to_goal_frame(*position).truncate());
+    // Aim inside the goal rather than at the arena's corners, which may be walled off.
+    Target::farthest_corner_from(opponent_position, arena.goal_width/2. - BALL_RADIUS)
+}
+
+fn get_required_transverse_velocity(contact_position: Vec4, outgoing_w_velocity: f32, target: Target, arena: &Arena) -> Vec3 {
+    let travel_time = arena.length / outgoing_w_velocity.abs();
+    (target.position() - contact_position.truncate()) / travel_time
+}
+
+fn get_aiming_position(arrival_position: Vec4, arrival_velocity: Vec4, target: Target, paddle_radius: f32, arena: &Arena) -> Vec3 {
+    // Invert deflect_off_paddle: work out which contact offset sends the ball to the target and stand there.
+    let required_velocity = get_required_transverse_velocity(arrival_position, arrival_velocity.w, target, arena);
+    let contact_offset = ((required_velocity - arrival_velocity.truncate()) / PADDLE_DEFLECTION_SPEED).clamp(-Vec3::ONE, Vec3::ONE);
+    arrival_position.truncate() - contact_offset * paddle_radius
+}
+
+fn predict_ball_arrival(position: Vec4, velocity: Vec4, plane_w: f32, arena: &Arena, topology: &Topology, fields: &ForceFields) -> Option<(Vec4, Vec4)> {
+    if !fields.is_empty() {
+        return simulate_ball_arrival(position, velocity, plane_w, arena, topology, fields);
+    }
+    if velocity.w == 0. {
+        return None;
+    }
+    let time_to_plane = (plane_w - position.w) / velocity.w;
+    if time_to_plane < 0. {
+        return None;
+    }
+
+    let unfolded_position = position + velocity * time_to_plane;
+    let mut arrival_position = unfolded_position;
+    let mut arrival_velocity = velocity;
+    let mut crossings = [0; 3];
+    for axis in Axis::TRANSVERSE {
+        let index = axis.index();
+        let half_extent = topology.get_wall_half_extent(axis, arena);
+        if topology.is_wrapping(axis) {
+            (arrival_position[index], crossings[index]) = wrap_into_arena(unfolded_position[index], half_extent);
+        } else {
+            (arrival_position[index], arrival_velocity[index]) = fold_into_arena(unfolded_position[index], velocity[index], half_extent);
+        }
+    }
+    let twist_mirror = get_twist_mirror(crossings, topology);
+    arrival_position.w = plane_w;
+    Some((arrival_position * twist_mirror, arrival_velocity * twist_mirror))
+}
+
+// The fields bend the path, so step along it instead.  Like the straight-line case, only the side walls are modelled.
+fn simulate_ball_arrival(position: Vec4, velocity: Vec4, plane_w: f32, arena: &Arena, topology: &Topology, fields: &ForceFields) -> Option<(Vec4, Vec4)> {
+    let mut position = position;
+    let mut velocity = velocity;
+    for _ in 0..(FORCE_FIELD_PREDICTION_SECONDS / FORCE_FIELD_STEP) as usize {
+        let (next_position, next_velocity) = step_ball(position, velocity, FORCE_FIELD_STEP, arena, topology, fields);
+        if (position.w - plane_w) * (next_position.w - plane_w) <= 0. && next_position.w != position.w {
+            // Close enough to where it crosses, since a step is so short.
+            let mut arrival_position = next_position;
+            arrival_position.w = plane_w;
+            return Some((arrival_position, next_velocity));
+        }
+        position = next_position;
+        velocity = next_velocity;
+    }
+    None
+}
+
+// Moves the ball on by one step, bouncing or wrapping it at the side walls.
+pub fn step_ball(position: Vec4, velocity: Vec4, delta_seconds: f32, arena: &Arena, topology: &Topology, fields: &ForceFields) -> (Vec4, Vec4) {
+    let (mut position, mut velocity) = integrate(position, velocity, delta_seconds, fields);
+    for axis in Axis::TRANSVERSE {
+        let index = axis.index();
+        let half_extent = topology.get_wall_half_extent(axis, arena);
+        if position[index].abs() <= half_extent {
+            continue;
+        }
+        if topology.is_wrapping(axis) {
+            (position, velocity) = wrap_on_axis(position, velocity, axis, arena, topology.get(axis));
+        } else {
+            (position[index], velocity[index]) = fold_into_arena(position[index], velocity[index], half_extent);
+        }
+    }
+    (position, velocity)
+}
+
+fn fold_into_arena(unfolded_position: f32, velocity: f32, half_extent: f32) -> (f32, f32) {
+    // Bouncing between walls at +-half_extent is a triangle wave with period 4 * half_extent.
+    let shifted = (unfolded_position + half_extent).rem_euclid(4. * half_extent);
+    if shifted <= 2. * half_extent {
+        (shifted - half_extent, velocity)
     } else {
-        None
+        (3. * half_extent - shifted, -velocity)
     }
 }
 
+pub fn get_velocity_towards(from: Vec3, to: Vec3, max_speed: Speed, delta_seconds: f32) -> Vec3 {
+    let displacement = to - from;
+    if delta_seconds <= 0. {
+        return displacement.normalize_or_zero() * max_speed;
+    }
+    let speed = max_speed.min(displacement.length() / delta_seconds);
+    displacement.normalize_or_zero() * speed
+}
+
+fn is_wall_collision(ball_position: Vec4, arena: &Arena, topology: &Topology, players: &[Player]) -> Option<Axis> {
+    let goal_axes = get_goal_axes(players);
+    let maybe_side_wall = Axis::TRANSVERSE.into_iter()
+        .filter(|axis| !goal_axes.contains(axis))
+        .find(|axis| ball_position[axis.index()].abs() > topology.get_wall_half_extent(*axis, arena));
+    if maybe_side_wall.is_some() {
+        return maybe_side_wall;
+    }
+    // The end walls around the goals, and across any goal with nobody defending it.
+    goal_axes.into_iter()
+        .find(|axis| {
+            let position = ball_position[axis.index()];
+            let is_open_goal = is_inside_goal(ball_position, *axis, arena) && is_goal_defended(players, *axis, position.signum());
+            position.abs() >= arena.get_goal_distance(*axis) && !is_open_goal
+        })
+}
+
+fn is_goal_defended(players: &[Player], axis: Axis, side: f32) -> bool {
+    players.iter().any(|player| player.get_goal_axis() == axis && player.get_goal_side() == side)
+}
+
+fn get_goal_axes(players: &[Player]) -> Vec<Axis> {
+    let mut goal_axes = Vec::new();
+    for player in players.iter() {
+        if !goal_axes.contains(&player.get_goal_axis()) {
+            goal_axes.push(player.get_goal_axis());
+        }
+    }
+    goal_axes
+}
+
 fn is_ball_paddle_collision(ball_position: Vec4, paddle_position: Vec4, paddle_size_modifier: SizeModifier) -> bool {
 
     let ball_radius = BALL_RADIUS;
@@ -342,35 +955,68 @@ fn is_ball_paddle_collision(ball_position: Vec4, paddle_position: Vec4, paddle_s
 
 }
 
-fn is_goal_collision(position: Vec4) -> Option<Player> {
-    let goal_distance = (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA) / 2.;
-    if position.w >= goal_distance {
-        Some(Player::Blue)
-    } else if position.w <= -goal_distance {
-        Some(Player::Red)
-    } else {
-        None
+// Returns the player defending the opposite goal, who scores in a head-to-head match.
+fn is_goal_collision(position: Vec4, arena: &Arena, players: &[Player]) -> Option<Player> {
+    players.iter()
+        .find(|player| {
+            let axis = player.get_goal_axis();
+            position[axis.index()] * player.get_goal_side() >= arena.get_goal_distance(axis) && is_inside_goal(position, axis, arena)
+        })
+        .map(|player| player.opponent())
+}
+
+fn is_inside_goal(position: Vec4, goal_axis: Axis, arena: &Arena) -> bool {
+    // A full-width goal has no wall around it to bounce off.
+    let mut across_goal = position;
+    across_goal[goal_axis.index()] = 0.;
+    arena.goal_width >= arena.width || across_goal.abs().max_element() <= arena.goal_width/2.
+}
+
+// With four players whoever put the ball in scores, unless it was an own goal or nobody has touched it yet, in which
+// case it goes to the player opposite.  With two players that's always the opponent.
+fn get_scorer(conceder: Player, last_hitter: Option<Player>) -> Player {
+    match last_hitter {
+        Some(hitter) if hitter != conceder => hitter,
+        _ => conceder.opponent(),
     }
 }
 
+// With relativity on, paddles hold their proper velocity (see movement_system) rather than what the arena sees.
+fn get_arena_velocity(velocity: &VelocityComponent, maybe_paddle: Option<&PaddleComponent>, relativity: &Relativity) -> Vec4 {
+    match maybe_paddle {
+        Some(_) => relativity.from_proper_velocity(velocity.0),
+        None => velocity.0,
+    }
+}
 
-fn get_color_from_w(w: f32, arena_length: f32) -> Color {
-    let blue = Color::BLUE.as_hsla_f32();
-    let red = Color::RED.as_hsla_f32();
-    let saturation = 1.0;
-    let lightness = 0.5;
-    let alpha = blue[3];
-    let factor = (w + arena_length / 2.) / arena_length;
-    Color::Hsla {
-        hue: lerp(blue[0], red[0], factor),
-        saturation: saturation,
-        lightness: lightness,
-        alpha: alpha
+// How far an object reaches along each axis, for the objects whose mesh gets stretched to fit.  Paddles are flat along
+// their goal axis, where the mesh keeps its own thickness.
+fn get_extents(maybe_obstacle: Option<&ObstacleComponent>, maybe_brick: Option<&BrickComponent>, maybe_paddle: Option<&PaddleComponent>, scale_modifier: f32) -> Option<Vec4> {
+    match (maybe_obstacle, maybe_brick, maybe_paddle) {
+        (Some(obstacle_component), _, _) => Some(obstacle_component.0.get_half_extents()),
+        (None, Some(brick_component), _) => Some(brick_component.half_extents),
+        (None, None, Some(paddle_component)) => Some(paddle_component.0.to_goal_frame(Vec3::splat(scale_modifier).extend(1.))),
+        (None, None, None) => None,
     }
 }
 
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    a + (b - a) * t
+pub fn get_color_from_w(w: f32, arena_length: f32, color_map: ColorMap) -> Color {
+    let factor = (w + arena_length / 2.) / arena_length;
+    color_map.get_color(factor)
+}
+
+pub fn get_w_from_color(color: Color, arena_length: f32, color_map: ColorMap) -> f32 {
+    let factor = color_map.get_t(color);
+    factor * arena_length - arena_length / 2.
+}
+
+fn get_obstacle_color(obstacle_component: &ObstacleComponent, position: Vec4, ball_position: Vec4, view_mapping: &ViewMapping, color_extent: f32, color_map: ColorMap) -> Color {
+    // An obstacle spans a range of w, so show the part of it nearest the ball: when the colours match, the ball can hit it.
+    let (min_w, max_w) = obstacle_component.0.get_range(position, view_mapping.color_axis);
+    let ball_w = ball_position[view_mapping.color_axis.index()];
+    let mut color = get_color_from_w(ball_w.clamp(min_w, max_w), color_extent, color_map);
+    color.set_a(OBSTACLE_ALPHA);
+    color
 }
 
 fn clamp_3d(
@@ -393,7 +1039,7 @@ fn clamp_3d(
 mod test_pong_plugin {
     use bevy::{asset::AssetPlugin, gltf::GltfPlugin, window::WindowPlugin, input::InputPlugin};
 
-    use crate::pong::{ui::UIPlugin, assets::LoadAssetsPlugin};
+    use crate::pong::{ui::UIPlugin, assets::LoadAssetsPlugin, actions::ActionsPlugin, controls::ControlsPlugin, powerups::PowerUpsPlugin, obstacles::ObstaclesPlugin, level::LevelPlugin, topology::TopologyPlugin, squash::SquashPlugin, breakout::BreakoutPlugin, campaign::CampaignPlugin, spacetime::SpacetimePlugin, relativity::RelativityPlugin, trajectory::TrajectoryPlugin, colormap::ColorMapPlugin};
 
     use super::*;
 
@@ -406,9 +1052,22 @@ mod test_pong_plugin {
             .add_plugin(InputPlugin)
             .add_plugin(GltfPlugin)
             .add_loopless_state(PongState::LoadingAssets)
+            .add_plugin(ActionsPlugin)
             .add_plugin(LoadAssetsPlugin)
             .add_plugin(UIPlugin)
             .add_plugin(PongPlugin)
+            .add_plugin(ControlsPlugin)
+            .add_plugin(PowerUpsPlugin)
+            .add_plugin(ObstaclesPlugin)
+            .add_plugin(LevelPlugin)
+            .add_plugin(TopologyPlugin)
+            .add_plugin(SquashPlugin)
+            .add_plugin(BreakoutPlugin)
+            .add_plugin(CampaignPlugin)
+            .add_plugin(SpacetimePlugin)
+            .add_plugin(RelativityPlugin)
+            .add_plugin(TrajectoryPlugin)
+            .add_plugin(ColorMapPlugin)
             .add_asset::<bevy::pbr::prelude::StandardMaterial>()
             .add_asset::<bevy::render::prelude::Mesh>()
             .add_asset::<bevy::scene::Scene>()
@@ -418,7 +1077,14 @@ mod test_pong_plugin {
         app.update();
         assert!(app.world.contains_resource::<GltfModel>());
         std::thread::sleep(std::time::Duration::from_millis(100)); // Allow time for assets to load.
-        app.update(); // PongState::LoadingAssets -> PongState::InGame
+        app.update(); // PongState::LoadingAssets -> PongState::LoadingUI
+        for _ in 0..10 {
+            if app.world.resource::<CurrentState<PongState>>().0 == PongState::Menu {
+                break;
+            }
+            app.update(); // PongState::LoadingUI -> PongState::Menu
+        }
+        app.world.insert_resource(NextState(PongState::InGame)); // Start a match against the AI on the next update.
 
         return app;
     }
@@ -430,24 +1096,51 @@ mod test_pong_plugin {
         assert!(app.world.contains_resource::<Time>());
         assert!(app.world.contains_resource::<AmbientLight>());
         
-        assert_eq!(app.world.query::<&PositionComponent>().iter(&app.world).count(), 3);
-        assert_eq!(app.world.query::<&MaterialHandleComponent>().iter(&app.world).count(), 3);
-        assert_eq!(app.world.query::<&VelocityComponent>().iter(&app.world).count(), 3);
+        // The ball, the serve marker and the two paddles, each with three projections.
+        assert_eq!(app.world.query_filtered::<&PositionComponent, Without<ProjectionComponent>>().iter(&app.world).count(), 4);
+        assert_eq!(app.world.query_filtered::<&MaterialHandleComponent, Without<ProjectionComponent>>().iter(&app.world).count(), 4);
+        assert_eq!(app.world.query::<&ProjectionComponent>().iter(&app.world).count(), 12);
+        assert_eq!(app.world.query::<&VelocityComponent>().iter(&app.world).count(), 4);
+        assert_eq!(app.world.query::<(&PositionComponent, &ServeMarkerComponent)>().iter(&app.world).count(), 1);
         assert_eq!(app.world.query::<(&mut VelocityComponent, &BallComponent)>().iter(&app.world).count(), 1);
         app.update(); // Should run on_entry systems for PongState::InGame
 
         for (velocity) in app.world.query::<&VelocityComponent>().iter(&app.world) {
             assert!(velocity.0.truncate().distance(Vec3::ZERO) < 0.0001);
         }
+        // The ball waits for the first serve.
+        assert!(!app.world.resource::<Serve>().is_in_play());
         for (velocity, _) in app.world.query::<(&VelocityComponent, &BallComponent)>().iter(&app.world) {
-            assert!(velocity.0.w == 1.0 || velocity.0.w == -1.0, "Velocity w component should be +1 or -1 but is: {}", velocity.0.w);;
-            assert_eq!(velocity.0.length(), 1.0);
+            assert_eq!(velocity.0, Vec4::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_serve_launches_ball() {
+        let mut app = initialize_pong_plugin_and_load_assets();
+        app.update();
+        assert_eq!(app.world.resource::<Serve>().server, Player::Blue);
+
+        app.world.resource_mut::<Serve>().phase = ServePhase::Ready;
+        app.update();
+        for (position, velocity, _) in app.world.query::<(&PositionComponent, &VelocityComponent, &BallComponent)>().iter(&app.world) {
+            assert!(position.0.w < 0., "The ball should wait on Blue's side but is at: {}", position.0);
+            assert_eq!(velocity.0, Vec4::ZERO);
+        }
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Space);
+        app.update();
+        assert!(app.world.resource::<Serve>().is_in_play());
+        for (velocity, _) in app.world.query::<(&VelocityComponent, &BallComponent)>().iter(&app.world) {
+            assert_eq!(velocity.0.w, 1.0);
         }
     }
 
     #[test]
     fn test_can_score_goal() {
         let mut app = initialize_pong_plugin_and_load_assets();
+        app.update();
+        app.world.insert_resource(Serve::in_play(Player::Blue));
 
         let new_ball_position = Vec4::new(0.0, 0.0, 0.0, (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA - 0.0001)/2.);
 
@@ -463,13 +1156,15 @@ mod test_pong_plugin {
         }
         app.update(); // Ball should have moved by more than 0.0001, so collision system should run, and player should have scored.
         app.update(); // Call a second time to ensure that collision system has run after ball movement
+        app.update(); // And once more so that the serve has placed the ball
 
         let mut ball_query = app.world.query::<(&PositionComponent, &VelocityComponent, &BallComponent)>();
         for (position, velocity, _) in ball_query.iter(&mut app.world) {
-            assert!(position.0.distance(Vec4::ZERO) < 0.1);
-            assert!(velocity.0.w == 1.0 || velocity.0.w == -1.0, "Velocity w component should be +1 or -1 but is: {}", velocity.0.w);;
-            assert_eq!(velocity.0.length(), 1.0);
+            assert!(position.0.w < 0., "The ball should be waiting for Blue's serve but is at: {}", position.0);
+            assert_eq!(velocity.0, Vec4::ZERO);
         }
+        assert_eq!(app.world.resource::<Serve>().server, Player::Blue);
+        assert!(!app.world.resource::<Serve>().is_in_play());
 
         let mut score_query = app.world.query::<&ScoreComponent>();
         for score_component in score_query.iter(&app.world) {
@@ -483,6 +1178,29 @@ mod test_pong_plugin {
 
     }
 
+    #[test]
+    fn test_winning_goal_ends_match() {
+        let mut app = initialize_pong_plugin_and_load_assets();
+        app.world.insert_resource(MatchRules { points_to_win: 1, win_by_two: false, sets_to_win: 1, serves_per_turn: 2 });
+        app.update();
+        app.world.insert_resource(Serve::in_play(Player::Blue));
+
+        let mut ball_query = app.world.query::<(&mut PositionComponent, &mut VelocityComponent, &BallComponent)>();
+        for (mut position, mut velocity, _) in ball_query.iter_mut(&mut app.world) {
+            position.0 = Vec4::new(0.0, 0.0, 0.0, (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA + 0.1)/2.);
+            velocity.0 = Vec4::new(0.0, 0.0, 0.0, 1.0);
+        }
+        app.update();
+        app.update();
+
+        assert_eq!(app.world.resource::<MatchScore>().winner(), Some(Player::Blue));
+        assert_eq!(app.world.resource::<CurrentState<PongState>>().0, PongState::GameOver);
+
+        app.world.insert_resource(NextState(PongState::InGame)); // Rematch
+        app.update();
+        assert_eq!(app.world.resource::<MatchScore>().winner(), None);
+    }
+
     #[test]
     fn test_input_handling() {
         let mut app = initialize_pong_plugin_and_load_assets();
@@ -531,13 +1249,148 @@ mod test_pong_plugin {
         }
     }
 
+    fn get_blue_paddle_velocity(app: &mut App) -> Vec4 {
+        let mut paddle_query = app.world.query::<(&VelocityComponent, &PaddleComponent)>();
+        paddle_query.iter(&app.world)
+            .find(|(_, paddle)| paddle.0 == Player::Blue)
+            .expect("The blue paddle should exist.")
+            .0.0
+    }
+
+    #[test]
+    fn test_input_survives_pause() {
+        let mut app = initialize_pong_plugin_and_load_assets();
+        app.update();
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+        app.update();
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), PADDLE_SPEED*Vec4::Z);
+
+        app.world.insert_resource(NextState(PongState::Paused));
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), Vec4::ZERO);
+
+        app.world.resource_mut::<Input<KeyCode>>().release(KeyCode::W);
+        app.update();
+
+        app.world.insert_resource(NextState(PongState::InGame));
+        app.update();
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), Vec4::ZERO);
+    }
+
+    #[test]
+    fn test_input_survives_focus_loss() {
+        let mut app = initialize_pong_plugin_and_load_assets();
+        app.update();
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::D);
+        app.update();
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), PADDLE_SPEED*Vec4::X);
+
+        app.world.resource_mut::<Events<bevy::window::WindowFocused>>().send(
+            bevy::window::WindowFocused { id: bevy::window::WindowId::primary(), focused: false }
+        );
+        app.update();
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), Vec4::ZERO);
+        assert!(!app.world.resource::<Input<KeyCode>>().pressed(KeyCode::D));
+    }
+
+    #[test]
+    fn test_held_keys_combine() {
+        let mut app = initialize_pong_plugin_and_load_assets();
+        app.update();
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::S);
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Up);
+        app.update();
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), PADDLE_SPEED*Vec4::Y);
+
+        app.world.resource_mut::<Input<KeyCode>>().release(KeyCode::S);
+        app.update();
+        assert_eq!(get_blue_paddle_velocity(&mut app), PADDLE_SPEED*(Vec4::Y + Vec4::Z));
+    }
+
     #[test]
     fn test_is_goal_collision() {
-        assert_eq!(is_goal_collision(Vec4::ZERO), None);
-        assert_eq!(is_goal_collision(Vec4::new(0.0, 0.0, 0.0, (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA)/2.)), Some(Player::Blue));
-        assert_eq!(is_goal_collision(Vec4::new(0.0, 0.0, 0.0, -(ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA)/2.)), Some(Player::Red));
-        assert_eq!(is_goal_collision(Vec4::new(1.0, 2.0, 5.0, (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA + 100.)/2.)), Some(Player::Blue));
-        assert_eq!(is_goal_collision(Vec4::new(-5.0, -200.0, 6000.0, -(ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA + 100.)/2.)), Some(Player::Red));
+        let arena = Arena::default();
+        assert_eq!(is_goal_collision(Vec4::ZERO, &arena, &Player::HEAD_TO_HEAD), None);
+        assert_eq!(is_goal_collision(Vec4::new(0.0, 0.0, 0.0, (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA)/2.), &arena, &Player::HEAD_TO_HEAD), Some(Player::Blue));
+        assert_eq!(is_goal_collision(Vec4::new(0.0, 0.0, 0.0, -(ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA)/2.), &arena, &Player::HEAD_TO_HEAD), Some(Player::Red));
+        assert_eq!(is_goal_collision(Vec4::new(1.0, 2.0, 5.0, (ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA + 100.)/2.), &arena, &Player::HEAD_TO_HEAD), Some(Player::Blue));
+        assert_eq!(is_goal_collision(Vec4::new(-5.0, -200.0, 6000.0, -(ARENA_LENGTH + GOAL_OFFSET_FROM_ARENA + 100.)/2.), &arena, &Player::HEAD_TO_HEAD), Some(Player::Red));
+
+        let narrow_goals = Arena { goal_width: 1.0, ..Arena::default() };
+        let goal_distance = narrow_goals.get_goal_distance(Axis::W);
+        assert_eq!(is_goal_collision(Vec4::new(0.4, -0.4, 0.0, goal_distance), &narrow_goals, &Player::HEAD_TO_HEAD), Some(Player::Blue));
+        assert_eq!(is_goal_collision(Vec4::new(0.6, 0.0, 0.0, goal_distance), &narrow_goals, &Player::HEAD_TO_HEAD), None);
+        assert_eq!(is_wall_collision(Vec4::new(0.6, 0.0, 0.0, goal_distance), &narrow_goals, &Topology::default(), &Player::HEAD_TO_HEAD), Some(Axis::W));
+        assert_eq!(is_wall_collision(Vec4::new(0.4, 0.0, 0.0, goal_distance), &narrow_goals, &Topology::default(), &Player::HEAD_TO_HEAD), None);
+    }
+
+    #[test]
+    fn test_undefended_goal_is_a_wall() {
+        let arena = Arena::default();
+        let goal_distance = arena.get_goal_distance(Axis::W);
+        let players = GameMode::Squash.get_players();
+        assert_eq!(is_goal_collision(Vec4::new(0.0, 0.0, 0.0, goal_distance), &arena, players), None);
+        assert_eq!(is_wall_collision(Vec4::new(0.0, 0.0, 0.0, goal_distance), &arena, &Topology::default(), players), Some(Axis::W));
+        assert_eq!(is_goal_collision(Vec4::new(0.0, 0.0, 0.0, -goal_distance), &arena, players), Some(Player::Red));
+        assert_eq!(is_wall_collision(Vec4::new(0.0, 0.0, 0.0, -goal_distance), &arena, &Topology::default(), players), None);
+    }
+
+    #[test]
+    fn test_four_player_goals() {
+        let arena = Arena::default();
+        let x_goal_distance = arena.get_goal_distance(Axis::X);
+        let w_goal_distance = arena.get_goal_distance(Axis::W);
+        // Green defends -x and Yellow +x, so the ball going in at one end is a goal for the player at the other.
+        assert_eq!(is_goal_collision(Vec4::new(-x_goal_distance, 0., 0., 0.), &arena, &Player::ALL), Some(Player::Yellow));
+        assert_eq!(is_goal_collision(Vec4::new(x_goal_distance, 0., 0., 0.), &arena, &Player::ALL), Some(Player::Green));
+        assert_eq!(is_goal_collision(Vec4::new(0., 0., 0., w_goal_distance), &arena, &Player::ALL), Some(Player::Blue));
+        assert_eq!(is_goal_collision(Vec4::new(0., 0., 0., -w_goal_distance), &arena, &Player::ALL), Some(Player::Red));
+        // Without Green and Yellow the x ends are walls.
+        assert_eq!(is_goal_collision(Vec4::new(-x_goal_distance, 0., 0., 0.), &arena, &Player::HEAD_TO_HEAD), None);
+        assert_eq!(is_wall_collision(Vec4::new(-x_goal_distance, 0., 0., 0.), &arena, &Topology::default(), &Player::HEAD_TO_HEAD), Some(Axis::X));
+        assert_eq!(is_wall_collision(Vec4::new(-x_goal_distance, 0., 0., 0.), &arena, &Topology::default(), &Player::ALL), None);
+    }
+
+    #[test]
+    fn test_get_scorer() {
+        // Whoever put the ball in scores.
+        assert_eq!(get_scorer(Player::Green, Some(Player::Blue)), Player::Blue);
+        assert_eq!(get_scorer(Player::Yellow, Some(Player::Red)), Player::Red);
+        // An own goal or an untouched ball goes to the player opposite.
+        assert_eq!(get_scorer(Player::Green, Some(Player::Green)), Player::Yellow);
+        assert_eq!(get_scorer(Player::Yellow, None), Player::Green);
+        // Head to head it's always the opponent.
+        assert_eq!(get_scorer(Player::Red, Some(Player::Blue)), Player::Blue);
+        assert_eq!(get_scorer(Player::Red, Some(Player::Red)), Player::Blue);
+        assert_eq!(get_scorer(Player::Blue, None), Player::Red);
+    }
+
+    #[test]
+    fn test_x_goal_paddles_deflect_the_ball() {
+        let arena = Arena::default();
+        let paddle_radius = PADDLE_WIDTH/2.;
+        for player in [Player::Green, Player::Yellow] {
+            // Heading straight into the middle of the paddle, along x.
+            let paddle_position = arena.get_paddle_starting_position(player);
+            let ball_velocity = Vec4::new(player.get_goal_side(), 0., 0., 0.);
+            let framed_ball_velocity = player.to_goal_frame(ball_velocity);
+            let framed_paddle_position = player.to_goal_frame(paddle_position);
+            assert!(is_ball_paddle_collision(framed_paddle_position, framed_paddle_position, 1.0));
+            assert!(is_moving_towards_w(framed_ball_velocity, framed_paddle_position.w));
+            assert!(!is_moving_towards_w(player.to_goal_frame(-ball_velocity), framed_paddle_position.w));
+
+            let deflected_velocity = deflect_off_paddle(framed_paddle_position, framed_ball_velocity, framed_paddle_position, paddle_radius);
+            assert_eq!(player.to_goal_frame(deflected_velocity), -ball_velocity, "{} should send the ball back along x", player);
+        }
     }
 
     #[test]
@@ -572,10 +1425,135 @@ mod test_pong_plugin {
         assert!(!is_towards_origin_3d(Vec4::new(1., 2., 3., 4.), -Vec4::new(-1., -1., -4., 4.)));
     }
 
+    #[test]
+    fn test_fold_into_arena() {
+        assert_eq!(fold_into_arena(0.5, 1.0, 1.0), (0.5, 1.0));
+        assert_eq!(fold_into_arena(1.5, 1.0, 1.0), (0.5, -1.0));
+        assert_eq!(fold_into_arena(-1.5, -1.0, 1.0), (-0.5, 1.0));
+        assert_eq!(fold_into_arena(4.5, 1.0, 1.0), (0.5, 1.0));
+    }
+
+    #[test]
+    fn test_predict_ball_arrival() {
+        let arena = Arena::default();
+        let topology = Topology::default();
+        assert_eq!(predict_ball_arrival(Vec4::ZERO, Vec4::new(0., 0., 0., -1.), ARENA_LENGTH/2., &arena, &topology, &ForceFields::default()), None);
+        assert_eq!(predict_ball_arrival(Vec4::ZERO, Vec4::new(1., 0., 0., 0.), ARENA_LENGTH/2., &arena, &topology, &ForceFields::default()), None);
+
+        let (position, velocity) = predict_ball_arrival(Vec4::ZERO, Vec4::new(0.1, -0.1, 0., 1.), ARENA_LENGTH/2., &arena, &topology, &ForceFields::default()).unwrap();
+        assert!(position.distance(Vec4::new(0.25, -0.25, 0., ARENA_LENGTH/2.)) < 0.0001);
+        assert_eq!(velocity, Vec4::new(0.1, -0.1, 0., 1.));
+
+        let (position, velocity) = predict_ball_arrival(Vec4::ZERO, Vec4::new(1., 0., 0., 1.), ARENA_LENGTH/2., &arena, &topology, &ForceFields::default()).unwrap();
+        assert!(position.x.abs() <= ARENA_WIDTH/2. - BALL_RADIUS);
+        assert_eq!(velocity.x, -1.);
+
+        let wrapping = Topology { x: WallBehaviour::Wrap, ..Topology::default() };
+        let (position, velocity) = predict_ball_arrival(Vec4::ZERO, Vec4::new(1., 0., 0., 1.), ARENA_LENGTH/2., &arena, &wrapping, &ForceFields::default()).unwrap();
+        assert!((position.x - 0.5).abs() < 0.0001);
+        assert_eq!(velocity.x, 1.);
+
+        let twisted = Topology { x: WallBehaviour::TwistedWrap, ..Topology::default() };
+        let (position, velocity) = predict_ball_arrival(Vec4::ZERO, Vec4::new(1., 0.1, 0., 1.), ARENA_LENGTH/2., &arena, &twisted, &ForceFields::default()).unwrap();
+        assert!(position.distance(Vec4::new(0.5, -0.25, 0., ARENA_LENGTH/2.)) < 0.0001);
+        assert_eq!(velocity, Vec4::new(1., -0.1, 0., 1.));
+    }
+
+    #[test]
+    fn test_predict_ball_arrival_with_fields() {
+        let arena = Arena::default();
+        let topology = Topology::default();
+
+        // Gravity along w brings a ball that's heading away back round to the paddle.
+        let gravity = ForceFields { gravity: Vec4::W, ..Default::default() };
+        let (position, velocity) = predict_ball_arrival(Vec4::ZERO, Vec4::new(0., 0., 0., -1.), ARENA_LENGTH/2., &arena, &topology, &gravity).unwrap();
+        assert_eq!(position.w, ARENA_LENGTH/2.);
+        assert!(velocity.w > 0.);
+
+        // Gravity along y drops the ball onto the floor and it bounces back up.
+        let gravity = ForceFields { gravity: -Vec4::Y, ..Default::default() };
+        let (position, _) = predict_ball_arrival(Vec4::ZERO, Vec4::W, ARENA_LENGTH/2., &arena, &topology, &gravity).unwrap();
+        assert!(position.y.abs() <= ARENA_WIDTH/2. - BALL_RADIUS);
+        assert!(position.y < 0.);
+
+        // Drag stops it short.
+        let drag = ForceFields { drag: 1., ..Default::default() };
+        assert_eq!(predict_ball_arrival(Vec4::ZERO, Vec4::W, ARENA_LENGTH/2., &arena, &topology, &drag), None);
+    }
+
+    #[test]
+    fn test_hit_wall() {
+        let arena = Arena::default();
+        let position = Vec4::new(0.99, 0., 0., 0.);
+        let velocity = Vec4::new(1., 0., 0., 1.);
+        assert_eq!(is_wall_collision(position, &arena, &Topology::default(), &Player::HEAD_TO_HEAD), Some(Axis::X));
+        assert_eq!(hit_wall(position, velocity, Axis::X, &arena, &Topology::default()), (position, Vec4::new(-1., 0., 0., 1.)));
+
+        // Wrapping walls let the ball through until its centre crosses them.
+        let wrapping = Topology { x: WallBehaviour::Wrap, ..Topology::default() };
+        assert_eq!(is_wall_collision(position, &arena, &wrapping, &Player::HEAD_TO_HEAD), None);
+        let through_wall = Vec4::new(1.01, 0., 0., 0.);
+        assert_eq!(is_wall_collision(through_wall, &arena, &wrapping, &Player::HEAD_TO_HEAD), Some(Axis::X));
+        let (wrapped_position, wrapped_velocity) = hit_wall(through_wall, velocity, Axis::X, &arena, &wrapping);
+        assert!((wrapped_position.x + 0.99).abs() < 0.0001);
+        assert_eq!(wrapped_velocity, velocity);
+    }
+
+    #[test]
+    fn test_deflect_off_paddle() {
+        let paddle_position = Vec4::new(0., 0., 0., ARENA_LENGTH/2.);
+        let paddle_radius = PADDLE_WIDTH/2.;
+
+        let centre_hit = deflect_off_paddle(paddle_position, Vec4::new(0., 0., 0., 1.), paddle_position, paddle_radius);
+        assert_eq!(centre_hit, Vec4::new(0., 0., 0., -1.));
+
+        let edge_hit = deflect_off_paddle(paddle_position + Vec4::new(paddle_radius, 0., 0., 0.), Vec4::new(0., 0., 0., 1.), paddle_position, paddle_radius);
+        assert_eq!(edge_hit, Vec4::new(PADDLE_DEFLECTION_SPEED, 0., 0., -1.));
+    }
+
+    #[test]
+    fn test_aiming_position_sends_ball_to_target() {
+        let arrival_position = Vec4::new(0.2, -0.1, 0.3, ARENA_LENGTH/2.);
+        let arrival_velocity = Vec4::new(0.1, 0.2, -0.1, 1.);
+        let paddle_radius = PADDLE_WIDTH * PADDLE_SIZE_MEDIUM/2.;
+        let target = Target::new(Vec3::new(-0.5, 0.5, 0.));
+
+        let paddle_position = get_aiming_position(arrival_position, arrival_velocity, target, paddle_radius, &Arena::default()).extend(ARENA_LENGTH/2.);
+        let outgoing_velocity = deflect_off_paddle(arrival_position, arrival_velocity, paddle_position, paddle_radius);
+        let travel_time = ARENA_LENGTH / outgoing_velocity.w.abs();
+        let landing_position = arrival_position.truncate() + outgoing_velocity.truncate() * travel_time;
+        assert!(landing_position.distance(target.position()) < 0.0001);
+    }
+
+    #[test]
+    fn test_get_velocity_towards() {
+        assert_eq!(get_velocity_towards(Vec3::ZERO, Vec3::X, 2.0, 0.1), Vec3::X * 2.0);
+        assert_eq!(get_velocity_towards(Vec3::ZERO, Vec3::X * 0.1, 2.0, 0.1), Vec3::X);
+        assert_eq!(get_velocity_towards(Vec3::ONE, Vec3::ONE, 2.0, 0.1), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_clamp_to_defence_zone() {
+        let arena = Arena::default();
+        let disabled = DefenceZone { enabled: false, depth: 1.0 };
+        assert_eq!(clamp_to_defence_zone(0.3, Player::Blue, &disabled, &arena), -ARENA_LENGTH/2.);
+        assert_eq!(clamp_to_defence_zone(0.3, Player::Red, &disabled, &arena), ARENA_LENGTH/2.);
+
+        let enabled = DefenceZone { enabled: true, depth: 1.0 };
+        assert_eq!(clamp_to_defence_zone(-ARENA_LENGTH/2. + 0.5, Player::Blue, &enabled, &arena), -ARENA_LENGTH/2. + 0.5);
+        assert_eq!(clamp_to_defence_zone(0., Player::Blue, &enabled, &arena), -ARENA_LENGTH/2. + 1.0);
+        assert_eq!(clamp_to_defence_zone(0., Player::Red, &enabled, &arena), ARENA_LENGTH/2. - 1.0);
+        assert_eq!(clamp_to_defence_zone(ARENA_LENGTH, Player::Red, &enabled, &arena), ARENA_LENGTH/2.);
+
+        let too_deep = DefenceZone { enabled: true, depth: ARENA_LENGTH };
+        assert!(clamp_to_defence_zone(0., Player::Blue, &too_deep, &arena) < 0.);
+        assert!(clamp_to_defence_zone(0., Player::Red, &too_deep, &arena) > 0.);
+    }
+
     #[test]
     fn test_get_color_from_w() {
-        assert_eq!(get_color_from_w(-ARENA_LENGTH/2., ARENA_LENGTH), Color::BLUE.as_hsla());
-        assert_eq!(get_color_from_w(ARENA_LENGTH/2., ARENA_LENGTH), Color::RED.as_hsla());
+        assert_eq!(get_color_from_w(-ARENA_LENGTH/2., ARENA_LENGTH, ColorMap::Classic), Color::BLUE.as_hsla());
+        assert_eq!(get_color_from_w(ARENA_LENGTH/2., ARENA_LENGTH, ColorMap::Classic), Color::RED.as_hsla());
     }
 
     #[test]
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use rand::Rng;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::pong::assets::{add_w_coloured_material, despawn_object_and_projections, spawn_object_and_projections};
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::player::Player;
+use crate::pong::pong::{PaddleHitEvent, ScoreEvent};
+use crate::pong::resources::*;
+
+pub struct PowerUpsPlugin;
+
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PowerUpsEnabled::default())
+            .insert_resource(ActiveEffects::default())
+            .insert_resource(LastHitter::default())
+            .insert_resource(PowerUpSpawnTimer(Timer::from_seconds(POWER_UP_SPAWN_SECONDS, true)))
+            .add_startup_system(power_up_mesh_load_system)
+            .add_exit_system(PongState::Menu, clear_power_ups_system)
+            .add_exit_system(PongState::GameOver, clear_power_ups_system)
+            .add_system(last_hitter_system.run_in_state(PongState::InGame))
+            .add_system(power_up_spawn_system.run_in_state(PongState::InGame).run_if(power_ups_enabled))
+            .add_system(power_up_collection_system.run_in_state(PongState::InGame))
+            .add_system(effect_timer_system.run_in_state(PongState::InGame))
+            .add_system(paddle_size_system)
+            .add_system(w_invisibility_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    BigPaddle,
+    SlowBall,
+    WInvisibleBall,
+    InvertedControls,
+}
+
+impl PowerUpKind {
+
+    pub const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::BigPaddle,
+        PowerUpKind::SlowBall,
+        PowerUpKind::WInvisibleBall,
+        PowerUpKind::InvertedControls,
+    ];
+
+    // The player the effect lands on when the given player collects it.
+    pub fn get_affected_player(&self, collector: Player) -> Player {
+        match self {
+            PowerUpKind::InvertedControls => collector.opponent(),
+            _ => collector,
+        }
+    }
+}
+
+impl Display for PowerUpKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PowerUpKind::BigPaddle => write!(f, "Big Paddle"),
+            PowerUpKind::SlowBall => write!(f, "Slow Ball"),
+            PowerUpKind::WInvisibleBall => write!(f, "W-Invisible Ball"),
+            PowerUpKind::InvertedControls => write!(f, "Inverted Controls"),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct PowerUpComponent(pub PowerUpKind);
+
+// Resources
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub player: Player,
+    pub seconds_left: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ActiveEffects(pub Vec<ActiveEffect>);
+
+impl ActiveEffects {
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+
+    pub fn affects(&self, kind: PowerUpKind, player: Player) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind && effect.player == player)
+    }
+
+    pub fn get_ball_speed_multiplier(&self) -> f32 {
+        if self.is_active(PowerUpKind::SlowBall) {
+            POWER_UP_SLOW_BALL_FACTOR
+        } else {
+            1.
+        }
+    }
+
+    // The size picked for the arena, grown by Big Paddle but never wider than the arena.
+    pub fn get_paddle_scale(&self, player: Player, arena: &Arena) -> f32 {
+        let growth = if self.affects(PowerUpKind::BigPaddle, player) {
+            POWER_UP_PADDLE_GROWTH
+        } else {
+            1.
+        };
+        (arena.paddle_size * growth).min(arena.width / PADDLE_WIDTH)
+    }
+
+    pub fn get_control_sign(&self, player: Player) -> f32 {
+        if self.affects(PowerUpKind::InvertedControls, player) {
+            -1.
+        } else {
+            1.
+        }
+    }
+
+    // Returns false if the effect was already running, in which case its timer is just topped back up.
+    pub fn add(&mut self, kind: PowerUpKind, player: Player) -> bool {
+        match self.0.iter_mut().find(|effect| effect.kind == kind && effect.player == player) {
+            Some(effect) => {
+                effect.seconds_left = POWER_UP_DURATION_SECONDS;
+                false
+            },
+            None => {
+                self.0.push(ActiveEffect { kind, player, seconds_left: POWER_UP_DURATION_SECONDS });
+                true
+            }
+        }
+    }
+
+    pub fn tick(&mut self, delta_seconds: f32) -> Vec<ActiveEffect> {
+        for effect in self.0.iter_mut() {
+            effect.seconds_left -= delta_seconds;
+        }
+        let expired = self.0.iter().filter(|effect| effect.seconds_left <= 0.).copied().collect();
+        self.0.retain(|effect| effect.seconds_left > 0.);
+        expired
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LastHitter(pub Option<Player>);
+
+struct PowerUpSpawnTimer(Timer);
+
+struct PowerUpMeshes(HashMap<PowerUpKind, Handle<Mesh>>);
+
+// End Resources
+
+// Run Conditions
+
+fn power_ups_enabled(power_ups_enabled: Res<PowerUpsEnabled>) -> bool {
+    power_ups_enabled.0
+}
+
+// End Run Conditions
+
+// Systems
+
+fn power_up_mesh_load_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    // Each kind gets its own shape since colour is already taken up by w.
+    let power_up_meshes = HashMap::from([
+        (PowerUpKind::BigPaddle, meshes.add(Mesh::from(shape::Cube { size: POWER_UP_RADIUS * 1.5 }))),
+        (PowerUpKind::SlowBall, meshes.add(Mesh::from(shape::Torus { radius: POWER_UP_RADIUS * 0.7, ring_radius: POWER_UP_RADIUS * 0.3, ..default() }))),
+        (PowerUpKind::WInvisibleBall, meshes.add(Mesh::from(shape::Icosphere { radius: POWER_UP_RADIUS, subdivisions: 1 }))),
+        (PowerUpKind::InvertedControls, meshes.add(Mesh::from(shape::Capsule { radius: POWER_UP_RADIUS * 0.4, depth: POWER_UP_RADIUS, ..default() }))),
+    ]);
+    commands.insert_resource(PowerUpMeshes(power_up_meshes));
+}
+
+fn last_hitter_system(
+    mut last_hitter: ResMut<LastHitter>,
+    mut paddle_hit_event_reader: EventReader<PaddleHitEvent>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+) {
+    for paddle_hit_event in paddle_hit_event_reader.iter() {
+        last_hitter.0 = Some(paddle_hit_event.0);
+    }
+    for _ in score_event_reader.iter() {
+        last_hitter.0 = None;
+    }
+}
+
+fn power_up_spawn_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena: Res<Arena>,
+    mut spawn_timer: ResMut<PowerUpSpawnTimer>,
+    power_up_meshes: Res<PowerUpMeshes>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    power_up_query: Query<&PowerUpComponent>,
+) {
+    if !spawn_timer.0.tick(time.delta()).just_finished() || power_up_query.iter().count() >= POWER_UP_MAX_COUNT {
+        return;
+    }
+    let rng = &mut rand::thread_rng();
+    let kind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+    let half_width = arena.width/2. - POWER_UP_RADIUS;
+    // Keep power-ups out of the defence zones so that they have to be won in open play.
+    let max_w = (arena.length/2. - DEFENCE_ZONE_DEPTH_DEFAULT).max(POWER_UP_RADIUS);
+    let position = Vec4::new(
+        rng.gen_range(-half_width..half_width),
+        rng.gen_range(-half_width..half_width),
+        rng.gen_range(-half_width..half_width),
+        rng.gen_range(-max_w..max_w),
+    );
+    let material = add_w_coloured_material(&mut materials, AlphaMode::Opaque);
+    spawn_object_and_projections(
+        &mut commands,
+        &power_up_meshes.0[&kind],
+        &material,
+        position,
+        None,
+        PowerUpComponent(kind),
+        None::<AIComponent>,
+    );
+}
+
+fn power_up_collection_system(
+    mut commands: Commands,
+    last_hitter: Res<LastHitter>,
+    mut active_effects: ResMut<ActiveEffects>,
+    ball_query: Query<&PositionComponent, With<BallComponent>>,
+    power_up_query: Query<(Entity, &PositionComponent, &PowerUpComponent)>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    // Nobody has earned it until someone has hit the ball, so leave it where it is.
+    let collector = match last_hitter.0 {
+        Some(player) => player,
+        None => return,
+    };
+    for ball_position in ball_query.iter() {
+        for (entity, power_up_position, power_up_component) in power_up_query.iter() {
+            if ball_position.0.distance(power_up_position.0) > BALL_RADIUS + POWER_UP_RADIUS {
+                continue;
+            }
+            despawn_object_and_projections(&mut commands, entity, &projection_query);
+            let kind = power_up_component.0;
+            active_effects.add(kind, kind.get_affected_player(collector));
+        }
+    }
+}
+
+fn effect_timer_system(
+    time: Res<Time>,
+    mut active_effects: ResMut<ActiveEffects>,
+) {
+    active_effects.tick(time.delta_seconds());
+}
+
+fn paddle_size_system(
+    arena: Res<Arena>,
+    active_effects: Res<ActiveEffects>,
+    mut paddle_query: Query<(&PaddleComponent, &mut ScaleComponent)>,
+) {
+    for (paddle_component, mut scale) in paddle_query.iter_mut() {
+        let paddle_scale = active_effects.get_paddle_scale(paddle_component.0, &arena);
+        if scale.0 != paddle_scale {
+            scale.0 = paddle_scale;
+        }
+    }
+}
+
+fn w_invisibility_system(
+    active_effects: Res<ActiveEffects>,
+    ball_query: Query<Entity, With<BallComponent>>,
+    mut projection_query: Query<(&mut Visibility, &ProjectionComponent)>,
+) {
+    // The projections all have w as an axis, so they'd give the game away.
+    let is_visible = !active_effects.is_active(PowerUpKind::WInvisibleBall);
+    for (mut visibility, projection_component) in projection_query.iter_mut() {
+        if ball_query.get(projection_component.0).is_ok() && visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+fn clear_power_ups_system(
+    mut commands: Commands,
+    mut active_effects: ResMut<ActiveEffects>,
+    mut last_hitter: ResMut<LastHitter>,
+    power_up_query: Query<Entity, With<PowerUpComponent>>,
+    projection_query: Query<(Entity, &ProjectionComponent)>,
+) {
+    for entity in power_up_query.iter() {
+        despawn_object_and_projections(&mut commands, entity, &projection_query);
+    }
+    active_effects.0.clear();
+    last_hitter.0 = None;
+}
+
+// End Systems
+
+#[cfg(test)]
+mod test_powerups {
+    use super::*;
+
+    #[test]
+    fn test_active_effects_refresh_and_expire() {
+        let mut active_effects = ActiveEffects::default();
+        assert!(active_effects.add(PowerUpKind::SlowBall, Player::Blue));
+        assert_eq!(active_effects.get_ball_speed_multiplier(), POWER_UP_SLOW_BALL_FACTOR);
+
+        assert!(active_effects.tick(POWER_UP_DURATION_SECONDS - 1.).is_empty());
+        assert!(!active_effects.add(PowerUpKind::SlowBall, Player::Blue));
+        assert!(active_effects.tick(POWER_UP_DURATION_SECONDS - 1.).is_empty());
+
+        let expired = active_effects.tick(1.);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].kind, PowerUpKind::SlowBall);
+        assert_eq!(active_effects.get_ball_speed_multiplier(), 1.);
+    }
+
+    #[test]
+    fn test_inverted_controls_hit_the_opponent() {
+        let mut active_effects = ActiveEffects::default();
+        let kind = PowerUpKind::InvertedControls;
+        active_effects.add(kind, kind.get_affected_player(Player::Blue));
+        assert_eq!(active_effects.get_control_sign(Player::Blue), 1.);
+        assert_eq!(active_effects.get_control_sign(Player::Red), -1.);
+    }
+
+    #[test]
+    fn test_big_paddle_grows_from_the_chosen_size() {
+        let mut active_effects = ActiveEffects::default();
+        let mut arena = Arena { paddle_size: PADDLE_SIZE_HARD, ..Arena::default() };
+        active_effects.add(PowerUpKind::BigPaddle, Player::Blue);
+        assert_eq!(active_effects.get_paddle_scale(Player::Blue, &arena), PADDLE_SIZE_HARD * POWER_UP_PADDLE_GROWTH);
+        assert_eq!(active_effects.get_paddle_scale(Player::Red, &arena), PADDLE_SIZE_HARD);
+
+        // Picking a size while it's active is kept once it wears off.
+        arena.paddle_size = PADDLE_SIZE_MEDIUM;
+        active_effects.tick(POWER_UP_DURATION_SECONDS);
+        assert_eq!(active_effects.get_paddle_scale(Player::Blue, &arena), PADDLE_SIZE_MEDIUM);
+    }
+
+    #[test]
+    fn test_big_paddle_fits_in_the_arena() {
+        let mut active_effects = ActiveEffects::default();
+        let arena = Arena { width: PADDLE_WIDTH * PADDLE_SIZE_EASY, paddle_size: PADDLE_SIZE_EASY, ..Arena::default() };
+        active_effects.add(PowerUpKind::BigPaddle, Player::Blue);
+        assert_eq!(active_effects.get_paddle_scale(Player::Blue, &arena), arena.width / PADDLE_WIDTH);
+    }
+}
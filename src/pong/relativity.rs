@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::player::Player;
+use crate::pong::resources::*;
+use crate::pong::ui::get_player_color32;
+
+pub struct RelativityPlugin;
+
+impl Plugin for RelativityPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Relativity::default())
+            .insert_resource(Clocks::default())
+            .add_exit_system(PongState::Menu, reset_clocks_system)
+            .add_exit_system(PongState::GameOver, reset_clocks_system)
+            .add_system(proper_time_system.run_in_state(PongState::InGame).run_if(is_relativistic))
+            .add_system(relativity_ui_system.run_in_state(PongState::InGame).run_if(is_relativistic));
+    }
+}
+
+// Resources
+
+// With this on, nothing can reach the speed of light, velocities add the way special relativity says they do, and
+// moving things look shorter along their direction of motion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relativity {
+    pub enabled: bool,
+    pub speed_of_light: f32,
+}
+
+impl Default for Relativity {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed_of_light: RELATIVITY_SPEED_OF_LIGHT_DEFAULT,
+        }
+    }
+}
+
+impl Relativity {
+
+    pub fn get_gamma(&self, velocity: Vec4) -> f32 {
+        if !self.enabled {
+            return 1.;
+        }
+        let beta = (velocity.length() / self.speed_of_light).min(RELATIVITY_MAX_BETA);
+        1. / (1. - beta * beta).sqrt()
+    }
+
+    // Proper velocity is distance in the arena per second on the mover's own clock, so it has no upper limit.
+    pub fn get_gamma_from_proper_velocity(&self, proper_velocity: Vec4) -> f32 {
+        if !self.enabled {
+            return 1.;
+        }
+        (1. + proper_velocity.length_squared() / (self.speed_of_light * self.speed_of_light)).sqrt()
+    }
+
+    pub fn from_proper_velocity(&self, proper_velocity: Vec4) -> Vec4 {
+        proper_velocity / self.get_gamma_from_proper_velocity(proper_velocity)
+    }
+
+    // The velocity in the arena of something moving at `velocity` in a frame that itself moves at `frame_velocity`.
+    // Adding -u instead takes a velocity into the frame moving at u.
+    pub fn add_velocities(&self, frame_velocity: Vec4, velocity: Vec4) -> Vec4 {
+        if !self.enabled {
+            return frame_velocity + velocity;
+        }
+        let frame_velocity = self.limit_speed(frame_velocity);
+        let c_squared = self.speed_of_light * self.speed_of_light;
+        let gamma = self.get_gamma(frame_velocity);
+        let dot = frame_velocity.dot(velocity);
+        let sum = frame_velocity + velocity / gamma + (gamma / (c_squared * (1. + gamma))) * dot * frame_velocity;
+        self.limit_speed(sum / (1. + dot / c_squared))
+    }
+
+    pub fn limit_speed(&self, velocity: Vec4) -> Vec4 {
+        if !self.enabled {
+            return velocity;
+        }
+        velocity.clamp_length_max(RELATIVITY_MAX_BETA * self.speed_of_light)
+    }
+
+    // How much to scale each axis by.  Exact for motion along an axis, and close enough for the boxes and spheres
+    // here otherwise.
+    pub fn get_contraction(&self, velocity: Vec4) -> Vec4 {
+        let direction = velocity.normalize_or_zero();
+        Vec4::ONE - (1. - 1. / self.get_gamma(velocity)) * direction * direction
+    }
+}
+
+// How much time has passed in the arena and on each paddle since the match started.
+#[derive(Debug, Clone, Default)]
+pub struct Clocks {
+    pub arena: f32,
+    pub paddles: BTreeMap<Player, f32>,
+}
+
+// End Resources
+
+// Run Conditions
+
+fn is_relativistic(relativity: Res<Relativity>) -> bool {
+    relativity.enabled
+}
+
+// End Run Conditions
+
+// Systems
+
+fn reset_clocks_system(mut clocks: ResMut<Clocks>) {
+    *clocks = Clocks::default();
+}
+
+fn proper_time_system(
+    time: Res<Time>,
+    relativity: Res<Relativity>,
+    mut clocks: ResMut<Clocks>,
+    paddle_query: Query<(&VelocityComponent, &PaddleComponent)>,
+) {
+    clocks.arena += time.delta_seconds();
+    for (velocity, paddle_component) in paddle_query.iter() {
+        // Paddles hold their proper velocity, see movement_system.
+        let gamma = relativity.get_gamma_from_proper_velocity(velocity.0);
+        *clocks.paddles.entry(paddle_component.0).or_default() += time.delta_seconds() / gamma;
+    }
+}
+
+fn relativity_ui_system(
+    mut egui_ctx: ResMut<EguiContext>,
+    relativity: Res<Relativity>,
+    clocks: Res<Clocks>,
+    ball_query: Query<&VelocityComponent, With<BallComponent>>,
+    paddle_query: Query<(&VelocityComponent, &PaddleComponent)>,
+) {
+    let ball_velocity = ball_query.get_single().map_or(Vec4::ZERO, |velocity| velocity.0);
+    let mut paddles: Vec<(Player, Vec4)> = paddle_query.iter()
+        .map(|(velocity, paddle_component)| (paddle_component.0, relativity.from_proper_velocity(velocity.0)))
+        .collect();
+    paddles.sort_by_key(|(player, _)| *player);
+    egui::Area::new("relativity")
+        .anchor(egui::Align2::RIGHT_CENTER, egui::Vec2::ZERO)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.label(format!("c = {:.2}", relativity.speed_of_light));
+            ui.label(format!("Ball γ = {:.3}", relativity.get_gamma(ball_velocity)));
+            ui.label(format!("Arena Clock: {:.1}s", clocks.arena));
+            for (player, paddle_velocity) in paddles {
+                // The ball's speed as seen from the paddle, which is what decides how squashed it looks to them.
+                let relative_velocity = relativity.add_velocities(-paddle_velocity, ball_velocity);
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{}: γ = {:.3}, Ball γ = {:.3}, Clock: {:.1}s",
+                        player,
+                        relativity.get_gamma(paddle_velocity),
+                        relativity.get_gamma(relative_velocity),
+                        clocks.paddles.get(&player).copied().unwrap_or(0.),
+                    ))
+                    .color(get_player_color32(player))
+                );
+            }
+        });
+}
+
+// End Systems
+
+#[cfg(test)]
+mod test_relativity {
+    use super::*;
+
+    fn get_relativity() -> Relativity {
+        Relativity { enabled: true, speed_of_light: 2. }
+    }
+
+    #[test]
+    fn test_disabled_is_classical() {
+        let relativity = Relativity::default();
+        let u = Vec4::new(1., 0., 0., 3.);
+        let v = Vec4::new(0., 2., 0., 3.);
+        assert_eq!(relativity.get_gamma(u), 1.);
+        assert_eq!(relativity.add_velocities(u, v), u + v);
+        assert_eq!(relativity.limit_speed(10. * u), 10. * u);
+        assert_eq!(relativity.get_contraction(u), Vec4::ONE);
+    }
+
+    #[test]
+    fn test_collinear_velocity_addition() {
+        let relativity = get_relativity();
+        // Half the speed of light twice over makes 4c/5, not c.
+        let sum = relativity.add_velocities(Vec4::W, Vec4::W);
+        assert!((sum - 1.6 * Vec4::W).length() < 0.0001);
+        // Taking a velocity into its own frame leaves it at rest.
+        let velocity = Vec4::new(0.3, -0.2, 0.1, 1.2);
+        assert!(relativity.add_velocities(-velocity, velocity).length() < 0.0001);
+    }
+
+    #[test]
+    fn test_nothing_reaches_the_speed_of_light() {
+        let relativity = get_relativity();
+        let fast = 1.9 * Vec4::W;
+        assert!(relativity.add_velocities(fast, fast).length() < relativity.speed_of_light);
+        assert!(relativity.add_velocities(fast, 1.9 * Vec4::X).length() < relativity.speed_of_light);
+        assert!(relativity.from_proper_velocity(1000. * Vec4::X).length() < relativity.speed_of_light);
+        assert!(relativity.limit_speed(10. * Vec4::X).length() < relativity.speed_of_light);
+    }
+
+    #[test]
+    fn test_gamma() {
+        let relativity = get_relativity();
+        assert_eq!(relativity.get_gamma(Vec4::ZERO), 1.);
+        assert!((relativity.get_gamma(Vec4::W * 1.6) - 5. / 3.).abs() < 0.0001);
+        // Proper velocity and velocity agree on gamma.
+        let proper_velocity = Vec4::new(1., 2., 0., 0.);
+        let velocity = relativity.from_proper_velocity(proper_velocity);
+        assert!((relativity.get_gamma(velocity) - relativity.get_gamma_from_proper_velocity(proper_velocity)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_get_contraction() {
+        let relativity = get_relativity();
+        let contraction = relativity.get_contraction(Vec4::W * 1.6);
+        assert!((contraction.w - 0.6).abs() < 0.0001);
+        assert_eq!(contraction.truncate(), Vec3::ONE);
+    }
+}
@@ -1,11 +1,22 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
 use bevy::{prelude::*, gltf::Gltf};
+use serde::{Deserialize, Serialize};
+
+use crate::pong::axis::Axis;
+use crate::pong::constants::*;
+use crate::pong::player::Player;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PongState {
     LoadingAssets,
     LoadingUI,
+    Menu,
     InGame,
     Paused,
+    GameOver,
+    Editor,
 }
 
 pub struct ProjectionImages(Handle<Image>, Handle<Image>, Handle<Image>);
@@ -22,3 +33,334 @@ impl ProjectionImages {
 }
 
 pub struct GltfModel(pub Handle<Gltf>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AimAssist(pub bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PowerUpsEnabled(pub bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ObstaclesEnabled(pub bool);
+
+// Whether the main 3D view is shown, or only the projections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ViewMode {
+    Full,
+    ProjectionsOnly,
+}
+
+impl ViewMode {
+
+    pub const ALL: [ViewMode; 2] = [ViewMode::Full, ViewMode::ProjectionsOnly];
+}
+
+impl Default for ViewMode {
+    fn default() -> Self {
+        ViewMode::Full
+    }
+}
+
+impl Display for ViewMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViewMode::Full => write!(f, "Full View"),
+            ViewMode::ProjectionsOnly => write!(f, "Projections Only"),
+        }
+    }
+}
+
+// Matches won by each player, kept apart for each view mode since projection-only matches are much harder.
+#[derive(Debug, Clone, Default)]
+pub struct MatchWins(pub HashMap<ViewMode, HashMap<Player, usize>>);
+
+impl MatchWins {
+
+    pub fn get(&self, view_mode: ViewMode, player: Player) -> usize {
+        self.0.get(&view_mode).and_then(|wins| wins.get(&player)).copied().unwrap_or(0)
+    }
+
+    pub fn record_win(&mut self, view_mode: ViewMode, player: Player) {
+        *self.0.entry(view_mode).or_default().entry(player).or_default() += 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServePhase {
+    Countdown(f32),
+    Ready,
+    InPlay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Serve {
+    pub server: Player,
+    pub phase: ServePhase,
+    pub launch_velocity: Vec4,
+}
+
+impl Serve {
+
+    pub fn new(server: Player) -> Self {
+        Self {
+            server,
+            phase: ServePhase::Countdown(SERVE_COUNTDOWN_SECONDS),
+            launch_velocity: Vec4::ZERO,
+        }
+    }
+
+    pub fn in_play(server: Player) -> Self {
+        Self {
+            server,
+            phase: ServePhase::InPlay,
+            launch_velocity: Vec4::ZERO,
+        }
+    }
+
+    pub fn is_in_play(&self) -> bool {
+        self.phase == ServePhase::InPlay
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DefenceZone {
+    pub enabled: bool,
+    pub depth: f32,
+}
+
+impl Default for DefenceZone {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            depth: DEFENCE_ZONE_DEPTH_DEFAULT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Arena {
+    pub length: f32,
+    pub width: f32,
+    // The goal is a cube-shaped hole in each end wall.  Outside it the ball bounces back.
+    pub goal_width: f32,
+    pub paddle_size: f32,
+    // Where each paddle starts across its goal line.  Missing players start in the middle.
+    pub paddle_spawns: BTreeMap<Player, Vec3>,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            length: ARENA_LENGTH,
+            width: ARENA_WIDTH,
+            goal_width: ARENA_WIDTH,
+            paddle_size: PADDLE_SIZE_MEDIUM,
+            paddle_spawns: BTreeMap::from([
+                (Player::Blue, Vec3::new(0., 0., -PADDLE_STARTING_OFFSET)),
+                (Player::Red, Vec3::new(0., 0., PADDLE_STARTING_OFFSET)),
+            ]),
+        }
+    }
+}
+
+impl Arena {
+
+    pub fn get_half_extent(&self, axis: Axis) -> f32 {
+        match axis {
+            Axis::W => self.length / 2.,
+            _ => self.width / 2.,
+        }
+    }
+
+    // Where the player's paddle sits along their goal axis.
+    pub fn get_goal_line(&self, player: Player) -> f32 {
+        player.get_goal_side() * self.get_half_extent(player.get_goal_axis())
+    }
+
+    pub fn get_goal_distance(&self, axis: Axis) -> f32 {
+        self.get_half_extent(axis) + GOAL_OFFSET_FROM_ARENA / 2.
+    }
+
+    pub fn get_max_defence_depth(&self) -> f32 {
+        self.length / 2. - DEFENCE_ZONE_CENTRE_GAP
+    }
+
+    // Spawns are given across the player's goal, so Green and Yellow's are (w, y, z).
+    pub fn get_paddle_starting_position(&self, player: Player) -> Vec4 {
+        let spawn = self.paddle_spawns.get(&player).copied().unwrap_or(Vec3::ZERO);
+        player.to_goal_frame(spawn.extend(self.get_goal_line(player)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AIPerception {
+    FullInformation,
+    ProjectionsOnly,
+}
+
+impl Default for AIPerception {
+    fn default() -> Self {
+        AIPerception::FullInformation
+    }
+}
+
+impl Display for AIPerception {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AIPerception::FullInformation => write!(f, "Full Information"),
+            AIPerception::ProjectionsOnly => write!(f, "Projections Only"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AIRecord {
+    pub returns: usize,
+    pub misses: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AIPerformance(pub HashMap<AIPerception, AIRecord>);
+
+impl AIPerformance {
+
+    pub fn get(&self, perception: AIPerception) -> AIRecord {
+        self.0.get(&perception).copied().unwrap_or_default()
+    }
+
+    pub fn record_return(&mut self, perception: AIPerception) {
+        self.0.entry(perception).or_default().returns += 1;
+    }
+
+    pub fn record_miss(&mut self, perception: AIPerception) {
+        self.0.entry(perception).or_default().misses += 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    VersusAI,
+    HotSeat,
+    FourPlayer,
+    // Blue on their own, against a solid wall where Red's goal would be.
+    Squash,
+    // Blue on their own, breaking the bricks at the far end.
+    Breakout,
+    // Against the AI, with w as time rather than space.
+    Spacetime,
+}
+
+impl GameMode {
+
+    pub fn get_players(&self) -> &'static [Player] {
+        match self {
+            GameMode::VersusAI | GameMode::HotSeat | GameMode::Spacetime => &Player::HEAD_TO_HEAD,
+            GameMode::FourPlayer => &Player::ALL,
+            GameMode::Squash | GameMode::Breakout => &[Player::Blue],
+        }
+    }
+
+    // Single-player modes have no match to win, so a miss just means serving again.
+    pub fn is_solo(&self) -> bool {
+        self.get_players().len() == 1
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::VersusAI
+    }
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameMode::VersusAI => write!(f, "Play vs AI"),
+            GameMode::HotSeat => write!(f, "Two Players (Hot Seat)"),
+            GameMode::FourPlayer => write!(f, "Four Players vs AI"),
+            GameMode::Squash => write!(f, "Solo Squash"),
+            GameMode::Breakout => write!(f, "4D Breakout"),
+            GameMode::Spacetime => write!(f, "Spacetime Pong"),
+        }
+    }
+}
+
+// The main view can only show three axes, so the fourth is shown as colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewMapping {
+    pub color_axis: Axis,
+}
+
+impl Default for ViewMapping {
+    fn default() -> Self {
+        Self {
+            color_axis: Axis::W,
+        }
+    }
+}
+
+impl ViewMapping {
+
+    // Swaps the colour axis into w, leaving the three axes to show in 3D.  Swapping is its own inverse.
+    pub fn to_view(&self, vector: Vec4) -> Vec4 {
+        let mut view = vector;
+        view[self.color_axis.index()] = vector.w;
+        view.w = vector[self.color_axis.index()];
+        view
+    }
+
+    pub fn get_color_extent(&self, arena: &Arena) -> f32 {
+        2. * arena.get_half_extent(self.color_axis)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseSettings {
+    pub sensitivity: f32,
+    pub smoothing: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: MOUSE_SENSITIVITY_DEFAULT,
+            smoothing: MOUSE_SMOOTHING_DEFAULT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalogSettings {
+    pub deadzone: f32,
+    pub response_exponent: f32,
+}
+
+impl Default for AnalogSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: GAMEPAD_DEADZONE_DEFAULT,
+            response_exponent: GAMEPAD_RESPONSE_EXPONENT_DEFAULT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GamepadAssignments(pub HashMap<Player, Gamepad>);
+
+impl GamepadAssignments {
+
+    pub fn get(&self, player: Player) -> Option<Gamepad> {
+        self.0.get(&player).copied()
+    }
+
+    pub fn assign(&mut self, player: Player, gamepad: Gamepad) {
+        // A gamepad only ever drives one paddle.
+        self.0.retain(|_, assigned_gamepad| *assigned_gamepad != gamepad);
+        self.0.insert(player, gamepad);
+    }
+
+    pub fn unassign(&mut self, gamepad: Gamepad) {
+        self.0.retain(|_, assigned_gamepad| *assigned_gamepad != gamepad);
+    }
+}
\ No newline at end of file
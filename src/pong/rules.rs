@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::pong::constants::*;
+use crate::pong::player::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRules {
+    pub points_to_win: usize,
+    pub win_by_two: bool,
+    pub sets_to_win: usize,
+    pub serves_per_turn: usize,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            points_to_win: MATCH_POINTS_TO_WIN_DEFAULT,
+            win_by_two: true,
+            sets_to_win: 1,
+            serves_per_turn: SERVES_PER_TURN_DEFAULT,
+        }
+    }
+}
+
+// What was picked in the menu.  MatchRules is this with the selected level's overrides on top, so switching levels
+// never carries one level's overrides over to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BaseMatchRules(pub MatchRules);
+
+impl Display for MatchRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "First to {}", self.points_to_win)?;
+        if self.win_by_two {
+            write!(f, ", win by two")?;
+        }
+        if self.sets_to_win > 1 {
+            write!(f, ", best of {} sets", 2 * self.sets_to_win - 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointOutcome {
+    Point,
+    SetWon(Player),
+    MatchWon(Player),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchScore {
+    points: HashMap<Player, usize>,
+    sets: HashMap<Player, usize>,
+    // The points each finished set ended on, for the results screen.
+    set_points: Vec<HashMap<Player, usize>>,
+    points_played: usize,
+    first_server: Player,
+    // In serving order.
+    players: Vec<Player>,
+    winner: Option<Player>,
+}
+
+impl Default for MatchScore {
+    fn default() -> Self {
+        Self::new(Player::Blue)
+    }
+}
+
+impl MatchScore {
+
+    pub fn new(first_server: Player) -> Self {
+        Self {
+            points: HashMap::new(),
+            sets: HashMap::new(),
+            set_points: Vec::new(),
+            points_played: 0,
+            first_server,
+            players: Player::HEAD_TO_HEAD.to_vec(),
+            winner: None,
+        }
+    }
+
+    pub fn with_players(mut self, players: &[Player]) -> Self {
+        self.players = players.to_vec();
+        self
+    }
+
+    pub fn points(&self, player: Player) -> usize {
+        self.points.get(&player).copied().unwrap_or(0)
+    }
+
+    pub fn sets(&self, player: Player) -> usize {
+        self.sets.get(&player).copied().unwrap_or(0)
+    }
+
+    pub fn finished_sets(&self) -> usize {
+        self.set_points.len()
+    }
+
+    pub fn set_points(&self, set_index: usize, player: Player) -> usize {
+        self.set_points.get(set_index).and_then(|points| points.get(&player)).copied().unwrap_or(0)
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        self.winner
+    }
+
+    pub fn award_point(&mut self, player: Player, rules: &MatchRules) -> PointOutcome {
+        if let Some(winner) = self.winner {
+            return PointOutcome::MatchWon(winner);
+        }
+        *self.points.entry(player).or_default() += 1;
+        self.points_played += 1;
+        if !self.has_won_set(player, rules) {
+            return PointOutcome::Point;
+        }
+
+        self.set_points.push(self.points.clone());
+        *self.sets.entry(player).or_default() += 1;
+        if self.sets(player) >= rules.sets_to_win {
+            // Leave the points up so the final score stays on screen.
+            self.winner = Some(player);
+            PointOutcome::MatchWon(player)
+        } else {
+            self.points.clear();
+            PointOutcome::SetWon(player)
+        }
+    }
+
+    pub fn server(&self, rules: &MatchRules) -> Player {
+        // Once two players are a point from the set, the serve moves on every point like in table tennis.
+        let is_deuce = rules.win_by_two && self.players.iter()
+            .filter(|player| self.points(**player) + 1 >= rules.points_to_win)
+            .count() >= 2;
+        let turns = if is_deuce {
+            self.points_played
+        } else {
+            self.points_played / rules.serves_per_turn.max(1)
+        };
+        let first_index = self.players.iter().position(|player| *player == self.first_server).unwrap_or(0);
+        self.players[(first_index + turns) % self.players.len()]
+    }
+
+    fn has_won_set(&self, player: Player, rules: &MatchRules) -> bool {
+        let points = self.points(player);
+        let opponent_points = self.players.iter()
+            .filter(|other_player| **other_player != player)
+            .map(|other_player| self.points(*other_player))
+            .max()
+            .unwrap_or(0);
+        let required_lead = if rules.win_by_two { 2 } else { 1 };
+        points >= rules.points_to_win && points >= opponent_points + required_lead
+    }
+}
+
+#[cfg(test)]
+mod test_rules {
+    use super::*;
+
+    #[test]
+    fn test_first_to_n() {
+        let rules = MatchRules { points_to_win: 3, win_by_two: false, sets_to_win: 1, serves_per_turn: 2 };
+        let mut score = MatchScore::default();
+        assert_eq!(score.award_point(Player::Blue, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Red, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Blue, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Blue, &rules), PointOutcome::MatchWon(Player::Blue));
+        assert_eq!(score.winner(), Some(Player::Blue));
+        assert_eq!(score.sets(Player::Blue), 1);
+        assert_eq!(score.points(Player::Blue), 3);
+        assert_eq!(score.points(Player::Red), 1);
+    }
+
+    #[test]
+    fn test_win_by_two() {
+        let rules = MatchRules { points_to_win: 2, win_by_two: true, sets_to_win: 1, serves_per_turn: 2 };
+        let mut score = MatchScore::default();
+        score.award_point(Player::Blue, &rules);
+        score.award_point(Player::Red, &rules);
+        assert_eq!(score.award_point(Player::Blue, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Red, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Red, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Red, &rules), PointOutcome::MatchWon(Player::Red));
+    }
+
+    #[test]
+    fn test_sets_reset_points() {
+        let rules = MatchRules { points_to_win: 1, win_by_two: false, sets_to_win: 2, serves_per_turn: 2 };
+        let mut score = MatchScore::default();
+        assert_eq!(score.award_point(Player::Red, &rules), PointOutcome::SetWon(Player::Red));
+        assert_eq!(score.points(Player::Red), 0);
+        assert_eq!(score.award_point(Player::Blue, &rules), PointOutcome::SetWon(Player::Blue));
+        assert_eq!(score.award_point(Player::Blue, &rules), PointOutcome::MatchWon(Player::Blue));
+        assert_eq!(score.award_point(Player::Red, &rules), PointOutcome::MatchWon(Player::Blue));
+        assert_eq!(score.sets(Player::Red), 1);
+        assert_eq!(score.finished_sets(), 3);
+        assert_eq!(score.set_points(0, Player::Red), 1);
+        assert_eq!(score.set_points(0, Player::Blue), 0);
+        assert_eq!(score.set_points(2, Player::Blue), 1);
+    }
+
+    #[test]
+    fn test_serve_alternates() {
+        let rules = MatchRules { points_to_win: 5, win_by_two: true, sets_to_win: 1, serves_per_turn: 2 };
+        let mut score = MatchScore::new(Player::Red);
+        assert_eq!(score.server(&rules), Player::Red);
+        score.award_point(Player::Blue, &rules);
+        assert_eq!(score.server(&rules), Player::Red);
+        score.award_point(Player::Blue, &rules);
+        assert_eq!(score.server(&rules), Player::Blue);
+        score.award_point(Player::Blue, &rules);
+        score.award_point(Player::Red, &rules);
+        assert_eq!(score.server(&rules), Player::Red);
+
+        // 4 - 4: deuce, so the serve changes every point.
+        for _ in 0..3 {
+            score.award_point(Player::Red, &rules);
+        }
+        score.award_point(Player::Blue, &rules);
+        assert_eq!(score.server(&rules), Player::Red);
+        score.award_point(Player::Blue, &rules);
+        assert_eq!(score.server(&rules), Player::Blue);
+    }
+
+    #[test]
+    fn test_four_players() {
+        let rules = MatchRules { points_to_win: 2, win_by_two: true, sets_to_win: 1, serves_per_turn: 1 };
+        let mut score = MatchScore::new(Player::Red).with_players(&Player::ALL);
+        assert_eq!(score.server(&rules), Player::Red);
+        score.award_point(Player::Green, &rules);
+        assert_eq!(score.server(&rules), Player::Green);
+        score.award_point(Player::Yellow, &rules);
+        assert_eq!(score.server(&rules), Player::Yellow);
+        score.award_point(Player::Blue, &rules);
+        assert_eq!(score.server(&rules), Player::Blue);
+
+        // Green needs a two point lead over the best of the rest.
+        assert_eq!(score.award_point(Player::Green, &rules), PointOutcome::Point);
+        assert_eq!(score.award_point(Player::Green, &rules), PointOutcome::MatchWon(Player::Green));
+    }
+}
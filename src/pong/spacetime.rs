@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::pong::assets::add_w_coloured_material;
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::resources::*;
+
+pub struct SpacetimePlugin;
+
+impl Plugin for SpacetimePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_exit_system(PongState::Menu, clear_world_lines_system)
+            .add_exit_system(PongState::GameOver, clear_world_lines_system)
+            .add_system(add_world_lines_system.run_if(is_spacetime))
+            .add_system(clock_system.run_in_state(PongState::InGame).run_if(is_spacetime))
+            .add_system(world_line_system.run_in_state(PongState::InGame).run_if(is_spacetime))
+            .add_system(world_line_marker_system);
+    }
+}
+
+// The recent path of the ball or a paddle through spacetime, oldest first.
+#[derive(Component, Debug, Clone, Default)]
+pub struct WorldLineComponent {
+    pub points: VecDeque<Vec4>,
+    pub seconds_since_point: f32,
+}
+
+impl WorldLineComponent {
+
+    pub fn record(&mut self, point: Vec4) {
+        self.points.push_back(point);
+        while self.points.len() > SPACETIME_WORLD_LINE_LENGTH {
+            self.points.pop_front();
+        }
+    }
+
+    // Counting back from the newest point.
+    pub fn get(&self, age: usize) -> Option<Vec4> {
+        self.points.len().checked_sub(age + 1).and_then(|index| self.points.get(index)).copied()
+    }
+}
+
+// One dot of a world-line, showing the point the given number of steps back from the newest.
+#[derive(Component, Clone, Copy)]
+pub struct WorldLineMarkerComponent {
+    pub source: Entity,
+    pub age: usize,
+}
+
+// Resources
+
+struct WorldLineMesh(Handle<Mesh>);
+
+// End Resources
+
+// Run Conditions
+
+fn is_spacetime(game_mode: Res<GameMode>) -> bool {
+    *game_mode == GameMode::Spacetime
+}
+
+// End Run Conditions
+
+// Systems
+
+fn add_world_lines_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    maybe_world_line_mesh: Option<Res<WorldLineMesh>>,
+    query: Query<Entity, (Or<(With<BallComponent>, With<PaddleComponent>)>, Without<WorldLineComponent>)>,
+) {
+    if query.is_empty() {
+        return;
+    }
+    let mesh = match maybe_world_line_mesh {
+        Some(world_line_mesh) => world_line_mesh.0.clone(),
+        None => {
+            let mesh = meshes.add(Mesh::from(shape::UVSphere { radius: SPACETIME_WORLD_LINE_RADIUS, ..default() }));
+            commands.insert_resource(WorldLineMesh(mesh.clone()));
+            mesh
+        }
+    };
+    for entity in query.iter() {
+        commands.entity(entity).insert(WorldLineComponent::default());
+        for age in 0..SPACETIME_WORLD_LINE_LENGTH {
+            let material = add_w_coloured_material(&mut materials, AlphaMode::Opaque);
+            commands.spawn_bundle(
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                }
+            )
+                .insert(WorldLineMarkerComponent { source: entity, age })
+                .insert(PositionComponent(Vec4::ZERO))
+                .insert(ScaleComponent(get_marker_scale(age)))
+                .insert(MaterialHandleComponent(material))
+                .insert(NeedsRenderingComponent);
+        }
+    }
+}
+
+fn clear_world_lines_system(
+    mut world_line_query: Query<&mut WorldLineComponent>,
+) {
+    for mut world_line in world_line_query.iter_mut() {
+        *world_line = WorldLineComponent::default();
+    }
+}
+
+fn clock_system(
+    arena: Res<Arena>,
+    serve: Res<Serve>,
+    mut ball_query: Query<&mut VelocityComponent, With<BallComponent>>,
+    mut paddle_query: Query<(&mut PositionComponent, &mut VelocityComponent, &PaddleComponent), Without<BallComponent>>,
+) {
+    // The ball's w is the clock, so it always ticks at the same rate.  A return just sends it back towards the
+    // other player's moment.
+    if serve.is_in_play() {
+        for mut velocity in ball_query.iter_mut() {
+            velocity.0.w = velocity.0.w.signum() * SPACETIME_CLOCK_RATE;
+        }
+    }
+    // Each paddle lives at its own moment, so it can move about in space but not in time.
+    for (mut position, mut velocity, paddle_component) in paddle_query.iter_mut() {
+        position.0.w = arena.get_goal_line(paddle_component.0);
+        velocity.0.w = 0.;
+    }
+}
+
+fn world_line_system(
+    time: Res<Time>,
+    serve: Res<Serve>,
+    ball_query: Query<&PositionComponent, With<BallComponent>>,
+    mut world_line_query: Query<(&PositionComponent, &mut WorldLineComponent, Option<&PaddleComponent>)>,
+) {
+    if !serve.is_in_play() {
+        return;
+    }
+    let clock = match ball_query.get_single() {
+        Ok(ball_position) => ball_position.0.w,
+        Err(_) => return,
+    };
+    for (position, mut world_line, maybe_paddle) in world_line_query.iter_mut() {
+        world_line.seconds_since_point += time.delta_seconds();
+        if world_line.seconds_since_point < SPACETIME_WORLD_LINE_INTERVAL {
+            continue;
+        }
+        world_line.seconds_since_point = 0.;
+        let point = match maybe_paddle {
+            Some(_) => get_paddle_event(position.0, clock),
+            None => position.0,
+        };
+        world_line.record(point);
+    }
+}
+
+fn world_line_marker_system(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    world_line_query: Query<&WorldLineComponent>,
+    mut marker_query: Query<(Entity, &WorldLineMarkerComponent, &mut PositionComponent, &mut Visibility)>,
+) {
+    let is_spacetime = *game_mode == GameMode::Spacetime;
+    for (entity, marker, mut position, mut visibility) in marker_query.iter_mut() {
+        let maybe_point = match world_line_query.get(marker.source) {
+            Ok(world_line) => world_line.get(marker.age),
+            Err(_) => {
+                // The paddle it followed has gone.
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        let is_visible = is_spacetime && maybe_point.is_some();
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+        position.0 = maybe_point.unwrap_or(position.0);
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+// A paddle's w is pinned to its own moment, so its world-line is drawn against the ball's clock instead: where it
+// was in space at each moment of the rally.  A return is where it crosses the ball's world-line at its own moment.
+pub fn get_paddle_event(paddle_position: Vec4, clock: f32) -> Vec4 {
+    paddle_position.truncate().extend(clock)
+}
+
+// Older dots shrink away so the direction of time along the line is easy to see.
+pub fn get_marker_scale(age: usize) -> f32 {
+    1. - age as f32 / SPACETIME_WORLD_LINE_LENGTH as f32
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_spacetime {
+    use super::*;
+
+    #[test]
+    fn test_world_line_keeps_the_newest_points() {
+        let mut world_line = WorldLineComponent::default();
+        assert_eq!(world_line.get(0), None);
+        for i in 0..(SPACETIME_WORLD_LINE_LENGTH + 5) {
+            world_line.record(Vec4::W * i as f32);
+        }
+        assert_eq!(world_line.points.len(), SPACETIME_WORLD_LINE_LENGTH);
+        assert_eq!(world_line.get(0), Some(Vec4::W * (SPACETIME_WORLD_LINE_LENGTH + 4) as f32));
+        assert_eq!(world_line.get(SPACETIME_WORLD_LINE_LENGTH - 1), Some(Vec4::W * 5.));
+        assert_eq!(world_line.get(SPACETIME_WORLD_LINE_LENGTH), None);
+    }
+
+    #[test]
+    fn test_get_paddle_event() {
+        let paddle_position = Vec4::new(0.1, 0.2, 0.3, -1.);
+        assert_eq!(get_paddle_event(paddle_position, 0.5), Vec4::new(0.1, 0.2, 0.3, 0.5));
+    }
+
+    #[test]
+    fn test_get_marker_scale() {
+        assert_eq!(get_marker_scale(0), 1.);
+        assert!(get_marker_scale(1) < get_marker_scale(0));
+        assert!(get_marker_scale(SPACETIME_WORLD_LINE_LENGTH - 1) > 0.);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::components::*;
+use crate::pong::config::{get_user_config_path, load_ron, save_ron};
+use crate::pong::constants::*;
+use crate::pong::player::Player;
+use crate::pong::pong::{PaddleHitEvent, ScoreEvent};
+use crate::pong::resources::*;
+
+pub const PERSONAL_BEST_FILE: &str = "squash.ron";
+
+pub struct SquashPlugin;
+
+impl Plugin for SquashPlugin {
+    fn build(&self, app: &mut App) {
+        let personal_best = load_ron(get_user_config_path(PERSONAL_BEST_FILE)).unwrap_or_else(|error| {
+            println!("Failed to load the squash personal best, starting from zero: {}", error);
+            PersonalBest::default()
+        });
+        app
+            .insert_resource(personal_best)
+            .insert_resource(Rally::default())
+            .add_exit_system(PongState::Menu, reset_rally_system)
+            .add_exit_system(PongState::GameOver, reset_rally_system)
+            .add_exit_system(PongState::InGame, save_personal_best_system)
+            .add_system(squash_return_system.run_in_state(PongState::InGame).run_if(is_squash))
+            .add_system(squash_miss_system.run_in_state(PongState::InGame).run_if(is_squash));
+    }
+}
+
+// Resources
+
+// The most consecutive returns ever made in squash for each view mode, kept between sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PersonalBest(pub BTreeMap<ViewMode, usize>);
+
+impl PersonalBest {
+
+    pub fn get(&self, view_mode: ViewMode) -> usize {
+        self.0.get(&view_mode).copied().unwrap_or(0)
+    }
+
+    // Returns whether it's a new best.
+    pub fn record(&mut self, view_mode: ViewMode, returns: usize) -> bool {
+        if returns <= self.get(view_mode) {
+            return false;
+        }
+        self.0.insert(view_mode, returns);
+        true
+    }
+}
+
+// Consecutive returns since the last miss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rally {
+    pub returns: usize,
+}
+
+// End Resources
+
+// Run Conditions
+
+fn is_squash(game_mode: Res<GameMode>) -> bool {
+    *game_mode == GameMode::Squash
+}
+
+// End Run Conditions
+
+// Systems
+
+fn reset_rally_system(mut rally: ResMut<Rally>) {
+    *rally = Rally::default();
+}
+
+fn squash_return_system(
+    view_mode: Res<ViewMode>,
+    mut rally: ResMut<Rally>,
+    mut personal_best: ResMut<PersonalBest>,
+    mut paddle_hit_event_reader: EventReader<PaddleHitEvent>,
+    mut ball_query: Query<&mut VelocityComponent, With<BallComponent>>,
+) {
+    for paddle_hit_event in paddle_hit_event_reader.iter() {
+        if paddle_hit_event.0 != Player::Blue {
+            continue;
+        }
+        rally.returns += 1;
+        for mut velocity in ball_query.iter_mut() {
+            velocity.0.w = velocity.0.w.signum() * get_squash_speed(rally.returns);
+        }
+        personal_best.record(*view_mode, rally.returns);
+    }
+}
+
+fn squash_miss_system(
+    view_mode: Res<ViewMode>,
+    mut rally: ResMut<Rally>,
+    personal_best: Res<PersonalBest>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+) {
+    if score_event_reader.iter().count() == 0 {
+        return;
+    }
+    save_if_record(&rally, &personal_best, *view_mode);
+    *rally = Rally::default();
+}
+
+fn save_personal_best_system(
+    game_mode: Res<GameMode>,
+    view_mode: Res<ViewMode>,
+    rally: Res<Rally>,
+    personal_best: Res<PersonalBest>,
+) {
+    // A rally can also end from the pause menu, and the game might be closed from there.
+    if *game_mode == GameMode::Squash {
+        save_if_record(&rally, &personal_best, *view_mode);
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+// Only write the record out when play stops, rather than on every return.
+fn save_if_record(rally: &Rally, personal_best: &PersonalBest, view_mode: ViewMode) {
+    if rally.returns > 0 && rally.returns == personal_best.get(view_mode) {
+        if let Err(error) = save_ron(personal_best, get_user_config_path(PERSONAL_BEST_FILE)) {
+            println!("Failed to save the squash personal best: {}", error);
+        }
+    }
+}
+
+// The ball's speed along w after the given number of returns.  The serve always goes out at 1.
+pub fn get_squash_speed(returns: usize) -> f32 {
+    (1. + returns as f32 * SQUASH_SPEED_UP_PER_RETURN).min(SQUASH_MAX_SPEED)
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_squash {
+    use super::*;
+
+    #[test]
+    fn test_get_squash_speed() {
+        assert_eq!(get_squash_speed(0), 1.);
+        assert!(get_squash_speed(1) > get_squash_speed(0));
+        assert!(get_squash_speed(10) > get_squash_speed(5));
+        assert_eq!(get_squash_speed(10000), SQUASH_MAX_SPEED);
+    }
+
+    #[test]
+    fn test_personal_best_per_view_mode() {
+        let mut personal_best = PersonalBest::default();
+        assert!(personal_best.record(ViewMode::Full, 10));
+        assert!(!personal_best.record(ViewMode::Full, 8));
+        assert!(personal_best.record(ViewMode::ProjectionsOnly, 3));
+        assert_eq!(personal_best.get(ViewMode::Full), 10);
+        assert_eq!(personal_best.get(ViewMode::ProjectionsOnly), 3);
+    }
+
+    #[test]
+    fn test_personal_best_save_and_load() {
+        let path = std::env::temp_dir().join("four-dimensional-pong-test-squash.ron");
+        let personal_best = PersonalBest(BTreeMap::from([(ViewMode::Full, 42), (ViewMode::ProjectionsOnly, 7)]));
+        save_ron(&personal_best, &path).unwrap();
+        assert_eq!(load_ron::<PersonalBest>(&path).unwrap(), personal_best);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
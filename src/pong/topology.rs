@@ -0,0 +1,277 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::assets::{add_w_coloured_material, spawn_object_and_projections};
+use crate::pong::axis::Axis;
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::resources::*;
+
+pub struct TopologyPlugin;
+
+impl Plugin for TopologyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Topology::default())
+            .add_exit_system(PongState::Menu, spawn_ghost_balls_system)
+            .add_system(ghost_ball_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WallBehaviour {
+    Reflect,
+    // Leaving through one face comes straight back in through the opposite one.
+    Wrap,
+    // As Wrap, but the opposite face is glued on after a half turn, so the other two transverse axes come out mirrored.
+    TwistedWrap,
+}
+
+impl WallBehaviour {
+
+    pub const ALL: [WallBehaviour; 3] = [
+        WallBehaviour::Reflect,
+        WallBehaviour::Wrap,
+        WallBehaviour::TwistedWrap,
+    ];
+
+    pub fn is_wrapping(&self) -> bool {
+        *self != WallBehaviour::Reflect
+    }
+}
+
+impl Default for WallBehaviour {
+    fn default() -> Self {
+        WallBehaviour::Reflect
+    }
+}
+
+impl Display for WallBehaviour {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WallBehaviour::Reflect => write!(f, "Reflect"),
+            WallBehaviour::Wrap => write!(f, "Wrap"),
+            WallBehaviour::TwistedWrap => write!(f, "Twisted Wrap"),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct GhostBallComponent(pub Axis);
+
+// Resources
+
+// How the side walls on each transverse axis behave.  The end walls hold the goals, so w always reflects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Topology {
+    pub x: WallBehaviour,
+    pub y: WallBehaviour,
+    pub z: WallBehaviour,
+}
+
+impl Topology {
+
+    pub fn get(&self, axis: Axis) -> WallBehaviour {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+            Axis::W => WallBehaviour::Reflect,
+        }
+    }
+
+    pub fn set(&mut self, axis: Axis, behaviour: WallBehaviour) {
+        match axis {
+            Axis::X => self.x = behaviour,
+            Axis::Y => self.y = behaviour,
+            Axis::Z => self.z = behaviour,
+            Axis::W => {
+                // Do nothing
+            }
+        }
+    }
+
+    pub fn is_wrapping(&self, axis: Axis) -> bool {
+        self.get(axis).is_wrapping()
+    }
+
+    // How far the ball's centre can get from the middle before it hits or crosses the wall on the given axis.
+    pub fn get_wall_half_extent(&self, axis: Axis, arena: &Arena) -> f32 {
+        if self.is_wrapping(axis) {
+            // The ball only wraps once its centre is through, so that it never shows in two places at once.
+            arena.width/2.
+        } else {
+            arena.width/2. - BALL_RADIUS
+        }
+    }
+}
+
+struct GhostBallMesh(Handle<Mesh>);
+
+// End Resources
+
+// Systems
+
+fn spawn_ghost_balls_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    maybe_ghost_ball_mesh: Option<Res<GhostBallMesh>>,
+    ghost_query: Query<&GhostBallComponent>,
+) {
+    if !ghost_query.is_empty() {
+        return;
+    }
+    let mesh = match maybe_ghost_ball_mesh {
+        Some(ghost_ball_mesh) => ghost_ball_mesh.0.clone(),
+        None => {
+            let mesh = meshes.add(Mesh::from(shape::UVSphere { radius: BALL_RADIUS, ..default() }));
+            commands.insert_resource(GhostBallMesh(mesh.clone()));
+            mesh
+        }
+    };
+    for axis in Axis::TRANSVERSE {
+        let material = add_w_coloured_material(&mut materials, AlphaMode::Blend);
+        spawn_object_and_projections(
+            &mut commands,
+            &mesh,
+            &material,
+            Vec4::ZERO,
+            None,
+            GhostBallComponent(axis),
+            None::<AIComponent>,
+        );
+    }
+}
+
+fn ghost_ball_system(
+    arena: Res<Arena>,
+    topology: Res<Topology>,
+    ball_query: Query<&PositionComponent, (With<BallComponent>, Without<GhostBallComponent>)>,
+    mut ghost_query: Query<(Entity, &mut PositionComponent, &mut Visibility, &GhostBallComponent), Without<BallComponent>>,
+    mut projection_query: Query<(&ProjectionComponent, &mut Visibility), Without<GhostBallComponent>>,
+) {
+    let ball_position = match ball_query.get_single() {
+        Ok(ball_position) => ball_position.0,
+        Err(_) => return,
+    };
+    for (entity, mut ghost_position, mut visibility, ghost_component) in ghost_query.iter_mut() {
+        let maybe_ghost_position = get_ghost_position(ball_position, ghost_component.0, &arena, &topology);
+        visibility.is_visible = maybe_ghost_position.is_some();
+        ghost_position.0 = maybe_ghost_position.unwrap_or(ball_position);
+        for (projection_component, mut projection_visibility) in projection_query.iter_mut() {
+            if projection_component.0 == entity {
+                projection_visibility.is_visible = visibility.is_visible;
+            }
+        }
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+// Which transverse axes get mirrored by a half turn about the given axis.
+fn get_half_turn_mirror(axis: Axis) -> Vec4 {
+    match axis {
+        Axis::X => Vec4::new(1., -1., -1., 1.),
+        Axis::Y => Vec4::new(-1., 1., -1., 1.),
+        Axis::Z => Vec4::new(-1., -1., 1., 1.),
+        Axis::W => Vec4::ONE,
+    }
+}
+
+pub fn wrap_on_axis(position: Vec4, velocity: Vec4, axis: Axis, arena: &Arena, behaviour: WallBehaviour) -> (Vec4, Vec4) {
+    let index = axis.index();
+    let mut wrapped_position = position;
+    wrapped_position[index] -= position[index].signum() * arena.width;
+    match behaviour {
+        WallBehaviour::TwistedWrap => {
+            let mirror = get_half_turn_mirror(axis);
+            (wrapped_position * mirror, velocity * mirror)
+        },
+        _ => (wrapped_position, velocity),
+    }
+}
+
+// Where the ball shows up on the far side of the given axis's walls, if it's close enough to be seen through them.
+pub fn get_ghost_position(ball_position: Vec4, axis: Axis, arena: &Arena, topology: &Topology) -> Option<Vec4> {
+    let behaviour = topology.get(axis);
+    if !behaviour.is_wrapping() || ball_position[axis.index()].abs() < arena.width/2. - GHOST_BALL_DISTANCE {
+        return None;
+    }
+    Some(wrap_on_axis(ball_position, Vec4::ZERO, axis, arena, behaviour).0)
+}
+
+// Where a ball travelling freely through wrapping walls ends up, along with how many times it went through them.
+pub fn wrap_into_arena(unfolded_position: f32, half_extent: f32) -> (f32, i32) {
+    let crossings = ((unfolded_position + half_extent) / (2. * half_extent)).floor();
+    (unfolded_position - crossings * 2. * half_extent, crossings as i32)
+}
+
+// A half turn about an axis negates the other two, and since the walls are the same on both sides that commutes with the
+// rest of the flight.  So the twists can be applied all at once at the end, as long as the number of crossings is known.
+pub fn get_twist_mirror(crossings: [i32; 3], topology: &Topology) -> Vec4 {
+    let mut mirror = Vec4::ONE;
+    for axis in Axis::TRANSVERSE {
+        if topology.get(axis) == WallBehaviour::TwistedWrap && crossings[axis.index()] % 2 != 0 {
+            mirror *= get_half_turn_mirror(axis);
+        }
+    }
+    mirror
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_topology {
+    use super::*;
+
+    #[test]
+    fn test_wrap_on_axis() {
+        let arena = Arena::default();
+        let position = Vec4::new(1.01, 0.2, -0.3, 0.5);
+        let velocity = Vec4::new(1., 0.5, 0.25, 1.);
+
+        let (wrapped_position, wrapped_velocity) = wrap_on_axis(position, velocity, Axis::X, &arena, WallBehaviour::Wrap);
+        assert!(wrapped_position.distance(Vec4::new(-0.99, 0.2, -0.3, 0.5)) < 0.0001);
+        assert_eq!(wrapped_velocity, velocity);
+
+        let (twisted_position, twisted_velocity) = wrap_on_axis(position, velocity, Axis::X, &arena, WallBehaviour::TwistedWrap);
+        assert!(twisted_position.distance(Vec4::new(-0.99, -0.2, 0.3, 0.5)) < 0.0001);
+        assert_eq!(twisted_velocity, Vec4::new(1., -0.5, -0.25, 1.));
+    }
+
+    #[test]
+    fn test_get_ghost_position() {
+        let arena = Arena::default();
+        let topology = Topology { y: WallBehaviour::Wrap, ..Topology::default() };
+        let near_top = Vec4::new(0.95, 0.95, 0., 0.);
+        assert_eq!(get_ghost_position(near_top, Axis::X, &arena, &topology), None);
+        let ghost = get_ghost_position(near_top, Axis::Y, &arena, &topology).unwrap();
+        assert!(ghost.distance(Vec4::new(0.95, -1.05, 0., 0.)) < 0.0001);
+        assert_eq!(get_ghost_position(Vec4::ZERO, Axis::Y, &arena, &topology), None);
+    }
+
+    #[test]
+    fn test_wrap_into_arena() {
+        assert_eq!(wrap_into_arena(0.5, 1.0), (0.5, 0));
+        assert_eq!(wrap_into_arena(1.5, 1.0), (-0.5, 1));
+        assert_eq!(wrap_into_arena(-1.5, 1.0), (0.5, -1));
+        assert_eq!(wrap_into_arena(4.5, 1.0), (0.5, 2));
+    }
+
+    #[test]
+    fn test_get_twist_mirror() {
+        let topology = Topology { x: WallBehaviour::TwistedWrap, y: WallBehaviour::Wrap, ..Topology::default() };
+        assert_eq!(get_twist_mirror([2, 1, 0], &topology), Vec4::ONE);
+        assert_eq!(get_twist_mirror([-1, 1, 0], &topology), Vec4::new(1., -1., -1., 1.));
+
+        let both_twisted = Topology { x: WallBehaviour::TwistedWrap, y: WallBehaviour::TwistedWrap, ..Topology::default() };
+        assert_eq!(get_twist_mirror([1, 1, 0], &both_twisted), Vec4::new(-1., -1., 1., 1.));
+    }
+}
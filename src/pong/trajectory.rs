@@ -0,0 +1,289 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::pong::assets::{add_w_coloured_material, spawn_object_and_projections};
+use crate::pong::axis::Axis;
+use crate::pong::components::*;
+use crate::pong::constants::*;
+use crate::pong::fields::ForceFields;
+use crate::pong::player::Player;
+use crate::pong::pong::step_ball;
+use crate::pong::relativity::Relativity;
+use crate::pong::resources::*;
+use crate::pong::topology::Topology;
+
+pub struct TrajectoryPlugin;
+
+impl Plugin for TrajectoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(TrajectoryGuide::default())
+            .add_exit_system(PongState::Menu, spawn_trajectory_system)
+            .add_exit_system(PongState::Editor, spawn_trajectory_system)
+            .add_system(trajectory_system);
+    }
+}
+
+// One dot along the guide line, counting out from the ball.
+#[derive(Component, Clone, Copy)]
+pub struct TrajectoryDotComponent(pub usize);
+
+// Where the ball will cross the defending paddle's plane.
+#[derive(Component, Clone, Copy)]
+pub struct TrajectoryMarkerComponent;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Trajectory {
+    pub dots: Vec<Vec4>,
+    pub crossing: Option<Vec4>,
+}
+
+// Resources
+
+// Training wheels: a dotted line showing where the ball is going, as far as the next paddle or the given time ahead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrajectoryGuide {
+    pub enabled: bool,
+    pub seconds: f32,
+}
+
+impl Default for TrajectoryGuide {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seconds: TRAJECTORY_SECONDS_DEFAULT,
+        }
+    }
+}
+
+struct TrajectoryMeshes {
+    dot: Handle<Mesh>,
+    marker: Handle<Mesh>,
+}
+
+// End Resources
+
+// Systems
+
+fn spawn_trajectory_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    maybe_trajectory_meshes: Option<Res<TrajectoryMeshes>>,
+    marker_query: Query<&TrajectoryMarkerComponent>,
+) {
+    if !marker_query.is_empty() {
+        return;
+    }
+    let (dot_mesh, marker_mesh) = match maybe_trajectory_meshes {
+        Some(trajectory_meshes) => (trajectory_meshes.dot.clone(), trajectory_meshes.marker.clone()),
+        None => {
+            let dot = meshes.add(Mesh::from(shape::UVSphere { radius: TRAJECTORY_DOT_RADIUS, ..default() }));
+            let marker = meshes.add(Mesh::from(shape::UVSphere { radius: TRAJECTORY_MARKER_RADIUS, ..default() }));
+            commands.insert_resource(TrajectoryMeshes { dot: dot.clone(), marker: marker.clone() });
+            (dot, marker)
+        }
+    };
+    for index in 0..TRAJECTORY_MAX_DOTS {
+        let material = add_w_coloured_material(&mut materials, AlphaMode::Opaque);
+        spawn_object_and_projections(
+            &mut commands,
+            &dot_mesh,
+            &material,
+            Vec4::ZERO,
+            None,
+            TrajectoryDotComponent(index),
+            None::<AIComponent>,
+        );
+    }
+    let material = add_w_coloured_material(&mut materials, AlphaMode::Opaque);
+    spawn_object_and_projections(
+        &mut commands,
+        &marker_mesh,
+        &material,
+        Vec4::ZERO,
+        None,
+        TrajectoryMarkerComponent,
+        None::<AIComponent>,
+    );
+}
+
+fn trajectory_system(
+    state: Res<CurrentState<PongState>>,
+    guide: Res<TrajectoryGuide>,
+    arena: Res<Arena>,
+    topology: Res<Topology>,
+    force_fields: Res<ForceFields>,
+    relativity: Res<Relativity>,
+    serve: Res<Serve>,
+    ball_query: Query<(&PositionComponent, &VelocityComponent), With<BallComponent>>,
+    paddle_query: Query<(&PositionComponent, &PaddleComponent), Without<BallComponent>>,
+    mut guide_query: Query<(Entity, &mut PositionComponent, Option<&TrajectoryDotComponent>), (Or<(With<TrajectoryDotComponent>, With<TrajectoryMarkerComponent>)>, Without<BallComponent>, Without<PaddleComponent>)>,
+    mut visibility_query: Query<(Entity, &mut Visibility, Option<&ProjectionComponent>)>,
+) {
+    let is_shown = guide.enabled && matches!(state.0, PongState::InGame | PongState::Paused);
+    let trajectory = match ball_query.get_single() {
+        Ok((ball_position, ball_velocity)) if is_shown => {
+            // Before the serve, show where it's about to go.
+            let velocity = if serve.is_in_play() { ball_velocity.0 } else { serve.launch_velocity };
+            // The ball is slowed to under the speed of light as it moves, so the guide has to be too.
+            let velocity = relativity.limit_speed(velocity);
+            let paddle_positions: Vec<(Player, Vec4)> = paddle_query.iter()
+                .map(|(position, paddle_component)| (paddle_component.0, position.0))
+                .collect();
+            get_trajectory(ball_position.0, velocity, &paddle_positions, guide.seconds, &arena, &topology, &force_fields)
+        },
+        _ => Trajectory::default(),
+    };
+
+    let mut guide_entities: Vec<Entity> = Vec::new();
+    let mut visible_entities: Vec<Entity> = Vec::new();
+    for (entity, mut position, maybe_dot) in guide_query.iter_mut() {
+        guide_entities.push(entity);
+        let maybe_point = match maybe_dot {
+            Some(dot) => trajectory.dots.get(dot.0).copied(),
+            None => trajectory.crossing,
+        };
+        match maybe_point {
+            Some(point) => {
+                position.0 = point;
+                visible_entities.push(entity);
+            },
+            None => {
+                // Do nothing
+            }
+        }
+    }
+    // The projections are separate entities, so hide those too.
+    for (entity, mut visibility, maybe_projection) in visibility_query.iter_mut() {
+        let source_entity = maybe_projection.map_or(entity, |projection_component| projection_component.0);
+        if !guide_entities.contains(&source_entity) {
+            continue;
+        }
+        let is_visible = visible_entities.contains(&source_entity);
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+// Steps the ball along as movement_system would, bouncing off the side walls, until it reaches the plane of the paddle
+// it's heading for or runs out of time.
+pub fn get_trajectory(
+    position: Vec4,
+    velocity: Vec4,
+    paddle_positions: &[(Player, Vec4)],
+    seconds: f32,
+    arena: &Arena,
+    topology: &Topology,
+    fields: &ForceFields,
+) -> Trajectory {
+    let mut trajectory = Trajectory::default();
+    if velocity == Vec4::ZERO {
+        return trajectory;
+    }
+    // Work in the defender's frame, where they defend a w goal, the same way the AI predicts the ball.
+    let maybe_defender = paddle_positions.iter()
+        .filter_map(|(player, paddle_position)| {
+            let plane_w = player.to_goal_frame(*paddle_position).w;
+            let framed_position = player.to_goal_frame(position);
+            let framed_velocity = player.to_goal_frame(velocity);
+            let time_to_plane = (plane_w - framed_position.w) / framed_velocity.w;
+            if framed_velocity.w * plane_w > 0. && time_to_plane >= 0. {
+                Some((*player, plane_w, time_to_plane))
+            } else {
+                None
+            }
+        })
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+    let to_frame = |vector: Vec4| maybe_defender.map_or(vector, |(player, _, _)| player.to_goal_frame(vector));
+    let fields = maybe_defender.map_or(fields.clone(), |(player, _, _)| fields.to_goal_frame(player));
+
+    let steps_per_dot = (TRAJECTORY_DOT_INTERVAL / FORCE_FIELD_STEP).round() as usize;
+    let mut position = to_frame(position);
+    let mut velocity = to_frame(velocity);
+    for step in 1..=(seconds / FORCE_FIELD_STEP).round() as usize {
+        let (next_position, next_velocity) = step_ball(position, velocity, FORCE_FIELD_STEP, arena, topology, &fields);
+        match maybe_defender {
+            Some((_, plane_w, _)) if (position.w - plane_w) * (next_position.w - plane_w) <= 0. => {
+                let mut crossing = next_position;
+                crossing.w = plane_w;
+                trajectory.crossing = Some(to_frame(crossing));
+                break;
+            },
+            _ => {
+                // Do nothing
+            }
+        }
+        // With nobody defending that end, it's a wall.
+        if next_position.w.abs() > arena.get_half_extent(Axis::W) {
+            break;
+        }
+        position = next_position;
+        velocity = next_velocity;
+        if step % steps_per_dot == 0 {
+            trajectory.dots.push(to_frame(position));
+        }
+    }
+    trajectory
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod test_trajectory {
+    use super::*;
+
+    fn get_paddle_positions(arena: &Arena) -> Vec<(Player, Vec4)> {
+        Player::HEAD_TO_HEAD.iter()
+            .map(|player| (*player, arena.get_paddle_starting_position(*player)))
+            .collect()
+    }
+
+    #[test]
+    fn test_trajectory_reaches_the_defending_paddle() {
+        let arena = Arena::default();
+        let topology = Topology::default();
+        let velocity = Vec4::new(0.5, -0.3, 0., 1.);
+        let trajectory = get_trajectory(Vec4::ZERO, velocity, &get_paddle_positions(&arena), TRAJECTORY_MAX_SECONDS, &arena, &topology, &ForceFields::default());
+        let crossing = trajectory.crossing.expect("The ball should reach Red's plane.");
+        assert_eq!(crossing.w, arena.get_goal_line(Player::Red));
+        assert!(!trajectory.dots.is_empty());
+        // The dots bounce off the side walls rather than going through them.
+        for point in trajectory.dots.iter().chain(std::iter::once(&crossing)) {
+            for axis in Axis::TRANSVERSE {
+                assert!(point[axis.index()].abs() <= topology.get_wall_half_extent(axis, &arena) + 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trajectory_length() {
+        let arena = Arena::default();
+        let seconds = 0.5;
+        let trajectory = get_trajectory(Vec4::ZERO, -Vec4::W, &get_paddle_positions(&arena), seconds, &arena, &Topology::default(), &ForceFields::default());
+        assert_eq!(trajectory.crossing, None);
+        assert_eq!(trajectory.dots.len(), (seconds / TRAJECTORY_DOT_INTERVAL).round() as usize);
+        assert!(trajectory.dots.iter().all(|point| point.w < 0.));
+    }
+
+    #[test]
+    fn test_trajectory_stops_at_an_undefended_end() {
+        let arena = Arena::default();
+        let blue_only = vec![(Player::Blue, arena.get_paddle_starting_position(Player::Blue))];
+        let trajectory = get_trajectory(Vec4::ZERO, Vec4::W, &blue_only, TRAJECTORY_MAX_SECONDS, &arena, &Topology::default(), &ForceFields::default());
+        assert_eq!(trajectory.crossing, None);
+        assert!(trajectory.dots.iter().all(|point| point.w <= arena.length / 2.));
+    }
+
+    #[test]
+    fn test_no_trajectory_when_still() {
+        let arena = Arena::default();
+        let trajectory = get_trajectory(Vec4::ZERO, Vec4::ZERO, &get_paddle_positions(&arena), TRAJECTORY_MAX_SECONDS, &arena, &Topology::default(), &ForceFields::default());
+        assert_eq!(trajectory, Trajectory::default());
+    }
+}
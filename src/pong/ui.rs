@@ -2,10 +2,23 @@ use bevy::{prelude::*};
 use bevy_egui::{egui, EguiContext, EguiPlugin};
 use iyes_loopless::prelude::*;
 
+use crate::pong::actions::*;
 use crate::pong::components::*;
 use crate::pong::resources::*;
 use crate::pong::constants::*;
 use crate::pong::player::Player;
+use crate::pong::rules::{BaseMatchRules, MatchRules, MatchScore};
+use crate::pong::powerups::ActiveEffects;
+use crate::pong::level::{Level, Levels, get_level_label};
+use crate::pong::axis::Axis;
+use crate::pong::topology::{Topology, WallBehaviour};
+use crate::pong::squash::{PersonalBest, Rally};
+use crate::pong::breakout::{BrickField, Lives};
+use crate::pong::campaign::Campaign;
+use crate::pong::relativity::Relativity;
+use crate::pong::trajectory::TrajectoryGuide;
+use crate::pong::colormap::ColorMap;
+use crate::pong::config::save_ron;
 
 pub struct UIPlugin;
 
@@ -14,12 +27,28 @@ impl Plugin for UIPlugin {
         app.add_plugin(EguiPlugin)
             .add_enter_system(PongState::LoadingUI, configure_visuals)
             .add_enter_system(PongState::LoadingUI, ui_load_system)
+            .add_system(menu_ui_system.run_in_state(PongState::Menu))
+            .add_system(game_over_ui_system.run_in_state(PongState::GameOver))
             .add_system(ui_system.run_in_state(PongState::InGame))
+            .insert_resource(RebindingScreen::default())
             .add_system(paused_ui_system.run_in_state(PongState::Paused))
+            .add_system(rebinding_ui_system.run_in_state(PongState::Paused))
+            .add_system(rebinding_input_system.run_in_state(PongState::Paused))
             .add_system(paused_input_system.run_in_state(PongState::Paused));
     }
 }
 
+// Resources
+
+#[derive(Debug, Clone, Default)]
+struct RebindingScreen {
+    is_open: bool,
+    awaiting: Option<PlayerAction>,
+    message: Option<String>,
+}
+
+// End Resources
+
 // Systems
 
 fn configure_visuals(mut egui_ctx: ResMut<EguiContext>) {
@@ -58,6 +87,14 @@ fn ui_load_system(
                     JustifyContent::SpaceBetween,
                 )
             ).insert(ScoreComponent(Player::Blue, 0));
+            // Green and Yellow only show up in four-player matches.
+            parent.spawn_bundle(
+                get_text_bundle(
+                    "0",
+                    get_text_style(font.clone(), Color::GREEN),
+                    JustifyContent::SpaceBetween,
+                )
+            ).insert(ScoreComponent(Player::Green, 0));
             parent.spawn_bundle(
                 get_text_bundle(
                     "4D Pong",
@@ -65,6 +102,13 @@ fn ui_load_system(
                     JustifyContent::SpaceBetween,
                 )
             );
+            parent.spawn_bundle(
+                get_text_bundle(
+                    "0",
+                    get_text_style(font.clone(), Color::YELLOW),
+                    JustifyContent::SpaceBetween,
+                )
+            ).insert(ScoreComponent(Player::Yellow, 0));
             parent.spawn_bundle(
                 get_text_bundle(
                     "0",
@@ -75,13 +119,213 @@ fn ui_load_system(
         }
     );
 
-    commands.insert_resource(NextState(PongState::InGame));
+    commands.insert_resource(NextState(PongState::Menu));
 
 }
 
+fn menu_ui_system(
+    mut commands: Commands,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut game_mode: ResMut<GameMode>,
+    mut view_mode: ResMut<ViewMode>,
+    mut color_map: ResMut<ColorMap>,
+    mut base_match_rules: ResMut<BaseMatchRules>,
+    match_rules: Res<MatchRules>,
+    mut power_ups_enabled: ResMut<PowerUpsEnabled>,
+    mut obstacles_enabled: ResMut<ObstaclesEnabled>,
+    mut topology: ResMut<Topology>,
+    mut relativity: ResMut<Relativity>,
+    mut levels: ResMut<Levels>,
+    level_assets: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+    key_bindings: Res<KeyBindings>,
+) {
+    egui::Area::new("main-menu")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(
+            egui_ctx.ctx_mut(),
+            |ui| {
+                ui.with_layout(
+                    egui::Layout::top_down(egui::Align::Center), |ui| {
+                        ui.label(
+                            egui::RichText::new("4D Pong")
+                            .size(30.)
+                            .text_style(egui::TextStyle::Heading)
+                            .color(egui::Color32::WHITE)
+                        );
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        for handle in levels.handles.clone() {
+                            let (label, is_loaded) = get_level_label(&level_assets, &asset_server, &handle);
+                            let is_selected = handle == levels.selected;
+                            if ui.add_enabled(is_loaded, egui::SelectableLabel::new(is_selected, label)).clicked() && !is_selected {
+                                levels.selected = handle;
+                            }
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        // Only written back when it changes, since the level's overrides are reapplied whenever it does.
+                        let mut rules = base_match_rules.0;
+                        ui.add(egui::Slider::new(&mut rules.points_to_win, 1..=MATCH_MAX_POINTS_TO_WIN).text("Points to Win"));
+                        ui.add(egui::Slider::new(&mut rules.sets_to_win, 1..=MATCH_MAX_SETS_TO_WIN).text("Sets to Win"));
+                        if toggle_button(ui, "Win by Two", rules.win_by_two) {
+                            rules.win_by_two = !rules.win_by_two;
+                        }
+                        if rules != base_match_rules.0 {
+                            base_match_rules.0 = rules;
+                        }
+                        if *match_rules != base_match_rules.0 {
+                            ui.label(format!("This level plays: {}", *match_rules));
+                        }
+                        if toggle_button(ui, "Power-Ups", power_ups_enabled.0) {
+                            power_ups_enabled.0 = !power_ups_enabled.0;
+                        }
+                        if toggle_button(ui, "Obstacles", obstacles_enabled.0) {
+                            obstacles_enabled.0 = !obstacles_enabled.0;
+                        }
+                        if toggle_button(ui, "Relativity", relativity.enabled) {
+                            relativity.enabled = !relativity.enabled;
+                        }
+                        if relativity.enabled {
+                            ui.add(egui::Slider::new(&mut relativity.speed_of_light, RELATIVITY_MIN_SPEED_OF_LIGHT..=RELATIVITY_MAX_SPEED_OF_LIGHT).text("Speed of Light"));
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("View:");
+                            for mode in ViewMode::ALL {
+                                if ui.selectable_label(*view_mode == mode, mode.to_string()).clicked() {
+                                    *view_mode = mode;
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Colours:");
+                            for map in ColorMap::ALL {
+                                if ui.selectable_label(*color_map == map, map.to_string()).clicked() {
+                                    *color_map = map;
+                                }
+                            }
+                        });
+                        for axis in Axis::TRANSVERSE {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{:?} Walls:", axis));
+                                for behaviour in WallBehaviour::ALL {
+                                    if ui.selectable_label(topology.get(axis) == behaviour, behaviour.to_string()).clicked() {
+                                        topology.set(axis, behaviour);
+                                    }
+                                }
+                            });
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        for mode in [GameMode::VersusAI, GameMode::HotSeat, GameMode::FourPlayer, GameMode::Squash, GameMode::Breakout, GameMode::Spacetime] {
+                            if ui.button(mode.to_string()).clicked() {
+                                *game_mode = mode;
+                                commands.insert_resource(NextState(PongState::InGame));
+                            }
+                        }
+                        if ui.button("Level Editor").clicked() {
+                            // Test runs are always against the AI.
+                            *game_mode = GameMode::VersusAI;
+                            commands.insert_resource(NextState(PongState::Editor));
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        for player in Player::HEAD_TO_HEAD {
+                            ui.label(
+                                egui::RichText::new(format!("{}: {}", player, key_bindings.describe(player)))
+                                .color(get_player_color32(player))
+                            );
+                        }
+                    }
+                );
+            }
+        );
+}
+
+fn game_over_ui_system(
+    mut commands: Commands,
+    mut egui_ctx: ResMut<EguiContext>,
+    game_mode: Res<GameMode>,
+    view_mode: Res<ViewMode>,
+    match_rules: Res<MatchRules>,
+    match_score: Res<MatchScore>,
+    match_wins: Res<MatchWins>,
+    brick_field: Res<BrickField>,
+    mut campaign: ResMut<Campaign>,
+) {
+    egui::Area::new("game-over-menu")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(
+            egui_ctx.ctx_mut(),
+            |ui| {
+                ui.with_layout(
+                    egui::Layout::top_down(egui::Align::Center), |ui| {
+                        let heading = match (*game_mode, match_score.winner()) {
+                            _ if campaign.is_passed => "Stage Complete!".to_string(),
+                            (GameMode::Breakout, _) if brick_field.remaining == 0 => "Cleared!".to_string(),
+                            (GameMode::Breakout, _) => format!("Out of Lives: {} Bricks Left", brick_field.remaining),
+                            (_, Some(player)) => format!("{} Wins!", player),
+                            (_, None) => "Game Over".to_string(),
+                        };
+                        ui.label(
+                            egui::RichText::new(heading)
+                            .size(30.)
+                            .text_style(egui::TextStyle::Heading)
+                            .color(match_score.winner().map_or(egui::Color32::WHITE, get_player_color32))
+                        );
+                        ui.label(match_rules.to_string());
+                        if match_score.finished_sets() > 0 {
+                            ui.label(get_final_score_text(&match_score, game_mode.get_players()));
+                        }
+                        if match_rules.sets_to_win > 1 {
+                            ui.label(get_sets_text(&match_score, game_mode.get_players()));
+                        }
+                        if !game_mode.is_solo() {
+                            let wins: Vec<String> = game_mode.get_players().iter()
+                                .map(|player| match_wins.get(*view_mode, *player).to_string())
+                                .collect();
+                            ui.label(format!("Matches Won ({}): {}", *view_mode, wins.join(" - ")));
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        match campaign.get_next_stage_index() {
+                            Some(next_stage_index) if campaign.is_passed => {
+                                if ui.button("Next Stage").clicked() {
+                                    campaign.stage_index = Some(next_stage_index);
+                                    commands.insert_resource(NextState(PongState::InGame));
+                                }
+                            },
+                            _ => {
+                                // Do nothing
+                            }
+                        }
+                        if ui.button("Rematch").clicked() {
+                            commands.insert_resource(NextState(PongState::InGame));
+                        }
+                        if ui.button("Main Menu").clicked() {
+                            commands.insert_resource(NextState(PongState::Menu));
+                        }
+                    }
+                );
+            }
+        );
+}
+
 fn ui_system(
     mut egui_ctx: ResMut<EguiContext>,
     projection_images: Res<ProjectionImages>,
+    key_bindings: Res<KeyBindings>,
+    game_mode: Res<GameMode>,
+    view_mode: Res<ViewMode>,
+    match_rules: Res<MatchRules>,
+    match_score: Res<MatchScore>,
+    serve: Res<Serve>,
+    active_effects: Res<ActiveEffects>,
+    rally: Res<Rally>,
+    personal_best: Res<PersonalBest>,
+    lives: Res<Lives>,
+    brick_field: Res<BrickField>,
+    player_input_query: Query<(&PaddleComponent, &PlayerInputComponent)>,
 ) {
     
     egui::TopBottomPanel::top("top_panel").show(egui_ctx.ctx_mut(), |ui| {
@@ -97,6 +341,88 @@ fn ui_system(
         });
     });
 
+    if *view_mode == ViewMode::ProjectionsOnly {
+        instantiate_projection_grid(&mut egui_ctx, &projection_images);
+    }
+
+    let mut human_players: Vec<(Player, ControlScheme)> = player_input_query.iter()
+        .map(|(paddle_component, player_input)| (paddle_component.0, player_input.0))
+        .collect();
+    human_players.sort_by_key(|(player, _)| *player);
+    egui::Area::new("control-hints")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(0., CONTROL_HINTS_OFFSET))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            for (player, control_scheme) in human_players {
+                ui.label(
+                    egui::RichText::new(format!("{}: {}", player, get_control_hint(&key_bindings, player, control_scheme)))
+                    .color(get_player_color32(player))
+                );
+            }
+        });
+
+    egui::Area::new("match-rules")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(0., CONTROL_HINTS_OFFSET))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            match *game_mode {
+                GameMode::Squash => {
+                    ui.label(format!("Returns: {}", rally.returns));
+                    ui.label(format!("Personal Best ({}): {}", *view_mode, personal_best.get(*view_mode)));
+                },
+                GameMode::Breakout => {
+                    ui.label(format!("Lives: {}", lives.0));
+                    ui.label(format!("Bricks: {}", brick_field.remaining));
+                },
+                _ => {
+                    ui.label(match_rules.to_string());
+                    if *view_mode == ViewMode::ProjectionsOnly {
+                        // The score text is underneath the projections.
+                        let points: Vec<String> = game_mode.get_players().iter()
+                            .map(|player| match_score.points(*player).to_string())
+                            .collect();
+                        ui.label(format!("Points: {}", points.join(" - ")));
+                    }
+                    if match_rules.sets_to_win > 1 {
+                        ui.label(get_sets_text(&match_score, game_mode.get_players()));
+                    }
+                }
+            }
+            for effect in active_effects.0.iter() {
+                ui.label(
+                    egui::RichText::new(format!("{} ({:.0}s)", effect.kind, effect.seconds_left.ceil()))
+                    .color(get_player_color32(effect.player))
+                );
+            }
+        });
+
+    let maybe_serve_text = match serve.phase {
+        ServePhase::Countdown(seconds_left) => Some(format!("{} serves in {}", serve.server, seconds_left.ceil())),
+        ServePhase::Ready => {
+            let key_text = key_bindings.get(serve.server, Action::Serve).map_or("-".to_string(), |key| format!("{:?}", key));
+            Some(format!("{} to serve: press {}", serve.server, key_text))
+        },
+        ServePhase::InPlay => None,
+    };
+    match maybe_serve_text {
+        Some(serve_text) => {
+            egui::Area::new("serve")
+                .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., CONTROL_HINTS_OFFSET))
+                .show(egui_ctx.ctx_mut(), |ui| {
+                    ui.label(
+                        egui::RichText::new(serve_text)
+                        .size(20.)
+                        .color(get_player_color32(serve.server))
+                    );
+                });
+        },
+        None => {
+            // Do nothing
+        }
+    }
+
+    if *view_mode == ViewMode::ProjectionsOnly {
+        // Already drawn, filling the screen.
+        return;
+    }
     let (xw_image, yw_image, zw_image) = projection_images.unpack();
 
     instantiate_projection_panel(&mut egui_ctx, xw_image, "xw-projection", "X-W Projection", egui::Align2::LEFT_BOTTOM);
@@ -107,8 +433,22 @@ fn ui_system(
 fn paused_ui_system(
     mut egui_ctx: ResMut<EguiContext>,
     mut ai_query: Query<&mut AIComponent>,
-    mut scale_query: Query<&mut ScaleComponent>,
+    mut aim_assist: ResMut<AimAssist>,
+    mut trajectory_guide: ResMut<TrajectoryGuide>,
+    mut arena: ResMut<Arena>,
+    mut defence_zone: ResMut<DefenceZone>,
+    mut ai_perception: ResMut<AIPerception>,
+    ai_performance: Res<AIPerformance>,
+    mut rebinding_screen: ResMut<RebindingScreen>,
+    mut mouse_settings: ResMut<MouseSettings>,
+    mut player_input_query: Query<(&PaddleComponent, &mut PlayerInputComponent, &mut VelocityComponent)>,
+    mut analog_settings: ResMut<AnalogSettings>,
+    mut gamepad_assignments: ResMut<GamepadAssignments>,
+    gamepads: Res<Gamepads>,
 ) {
+    if rebinding_screen.is_open {
+        return;
+    }
     egui::Area::new("pause-menu")
         .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
         .show(
@@ -124,32 +464,128 @@ fn paused_ui_system(
                             .color(egui::Color32::BLACK)
                         );
 
-                        ui.add_space(PAUSE_SCREEN_SPACING);
-                        let mut ai = ai_query.single_mut();
-                        let mut new_speed: Option<Speed> = None;
-                        new_speed = ai_speed_button(ui, "AI Speed Easy", AI_PADDLE_SPEED_EASY, ai.0).map_or(new_speed, |s| Some(s));
-                        new_speed = ai_speed_button(ui, "AI Speed Medium", AI_PADDLE_SPEED_MEDIUM, ai.0).map_or(new_speed, |s| Some(s));
-                        new_speed = ai_speed_button(ui, "AI Speed Hard", AI_PADDLE_SPEED_HARD, ai.0).map_or(new_speed, |s| Some(s));
-                        match new_speed {
-                            Some(speed) => {
-                                ai.0 = speed;
+                        // The buttons set every AI paddle to the same speed, so any of them shows the current one.
+                        match ai_query.iter().next().map(|ai| ai.0) {
+                            Some(current_speed) => {
+                                ui.add_space(PAUSE_SCREEN_SPACING);
+                                let mut new_speed: Option<Speed> = None;
+                                new_speed = ai_speed_button(ui, "AI Speed Easy", AI_PADDLE_SPEED_EASY, current_speed).map_or(new_speed, |s| Some(s));
+                                new_speed = ai_speed_button(ui, "AI Speed Medium", AI_PADDLE_SPEED_MEDIUM, current_speed).map_or(new_speed, |s| Some(s));
+                                new_speed = ai_speed_button(ui, "AI Speed Hard", AI_PADDLE_SPEED_HARD, current_speed).map_or(new_speed, |s| Some(s));
+                                match new_speed {
+                                    Some(speed) => {
+                                        for mut ai in ai_query.iter_mut() {
+                                            ai.0 = speed;
+                                        }
+                                    },
+                                    None => (),
+                                };
                             },
-                            None => (),
-                        };
+                            None => {
+                                // No AI when everyone is playing.
+                            }
+                        }
 
                         ui.add_space(PAUSE_SCREEN_SPACING);
-                        let current_scale = scale_query.iter().next().expect("We'll always have a scale component on a Paddle.").0;
+                        // Power-ups grow the paddles from this size (see paddle_size_system).
+                        let current_scale = arena.paddle_size;
                         let mut new_scale: Option<f32> = None;
                         new_scale = scale_button(ui, "Paddle Size Easy", PADDLE_SIZE_EASY, current_scale).map_or(new_scale, |s| Some(s));
                         new_scale = scale_button(ui, "Paddle Size Medium", PADDLE_SIZE_MEDIUM, current_scale).map_or(new_scale, |s| Some(s));
                         new_scale = scale_button(ui, "Paddle Size Hard", PADDLE_SIZE_HARD, current_scale).map_or(new_scale, |s| Some(s));
-                        for mut scale in scale_query.iter_mut() {
-                            match new_scale {
-                                Some(s) => {
-                                    scale.0 = s;
-                                },
-                                None => (),
-                            };
+                        match new_scale {
+                            Some(s) => {
+                                arena.paddle_size = s;
+                            },
+                            None => (),
+                        };
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        if toggle_button(ui, "Aim Assist", aim_assist.0) {
+                            aim_assist.0 = !aim_assist.0;
+                        }
+                        if toggle_button(ui, "Trajectory Guide", trajectory_guide.enabled) {
+                            trajectory_guide.enabled = !trajectory_guide.enabled;
+                        }
+                        if trajectory_guide.enabled {
+                            ui.add(egui::Slider::new(&mut trajectory_guide.seconds, TRAJECTORY_MIN_SECONDS..=TRAJECTORY_MAX_SECONDS).text("Guide Length (s)"));
+                        }
+                        if toggle_button(ui, "Move Along W", defence_zone.enabled) {
+                            defence_zone.enabled = !defence_zone.enabled;
+                        }
+                        if defence_zone.enabled {
+                            ui.add(egui::Slider::new(&mut defence_zone.depth, 0.0..=arena.get_max_defence_depth()).text("Defence Zone Depth"));
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        for perception in [AIPerception::FullInformation, AIPerception::ProjectionsOnly] {
+                            let record = ai_performance.get(perception);
+                            let text = format!("AI {} (Returns: {}, Misses: {})", perception, record.returns, record.misses);
+                            if selectable_button(ui, &text, perception, *ai_perception).is_some() {
+                                *ai_perception = perception;
+                            }
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        let mut human_players: Vec<(Player, ControlScheme)> = player_input_query.iter()
+                            .map(|(paddle_component, player_input, _)| (paddle_component.0, player_input.0))
+                            .collect();
+                        human_players.sort_by_key(|(player, _)| *player);
+                        let mut new_scheme: Option<(Player, ControlScheme)> = None;
+                        for (player, current_scheme) in human_players.iter() {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new(format!("{}:", player)).color(get_player_color32(*player)));
+                                for scheme in [ControlScheme::Keyboard, ControlScheme::Mouse, ControlScheme::Gamepad] {
+                                    if selectable_button(ui, &scheme.to_string(), scheme, *current_scheme).is_some() && scheme != *current_scheme {
+                                        new_scheme = Some((*player, scheme));
+                                    }
+                                }
+                            });
+                        }
+                        match new_scheme {
+                            Some((changed_player, scheme)) => {
+                                for (paddle_component, mut player_input, mut velocity) in player_input_query.iter_mut() {
+                                    // There's only one mouse, so whoever had it goes back to the keyboard.
+                                    if paddle_component.0 == changed_player {
+                                        player_input.0 = scheme;
+                                        velocity.0 = Vec4::ZERO;
+                                    } else if scheme == ControlScheme::Mouse && player_input.0 == ControlScheme::Mouse {
+                                        player_input.0 = ControlScheme::Keyboard;
+                                        velocity.0 = Vec4::ZERO;
+                                    }
+                                }
+                            },
+                            None => (),
+                        };
+                        let is_using_scheme = |scheme| human_players.iter().any(|(_, current_scheme)| *current_scheme == scheme);
+                        if is_using_scheme(ControlScheme::Mouse) {
+                            ui.add(egui::Slider::new(&mut mouse_settings.sensitivity, 0.5..=3.0).text("Mouse Sensitivity"));
+                            ui.add(egui::Slider::new(&mut mouse_settings.smoothing, 0.0..=MOUSE_MAX_SMOOTHING).text("Mouse Smoothing"));
+                        }
+                        if is_using_scheme(ControlScheme::Gamepad) {
+                            ui.add(egui::Slider::new(&mut analog_settings.deadzone, 0.0..=0.5).text("Stick Deadzone"));
+                            ui.add(egui::Slider::new(&mut analog_settings.response_exponent, 1.0..=3.0).text("Stick Response Curve"));
+                            let connected_gamepads: Vec<Gamepad> = gamepads.iter().copied().collect();
+                            for player in Player::HEAD_TO_HEAD {
+                                let assigned_gamepad = gamepad_assignments.get(player);
+                                let text = match assigned_gamepad {
+                                    Some(gamepad) => format!("{} Gamepad: #{}", player, gamepad.0),
+                                    None => format!("{} Gamepad: None", player),
+                                };
+                                if ui.button(text).clicked() {
+                                    match get_next_gamepad(&connected_gamepads, assigned_gamepad) {
+                                        Some(gamepad) => gamepad_assignments.assign(player, gamepad),
+                                        None => {
+                                            gamepad_assignments.0.remove(&player);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        if ui.button("Controls").clicked() {
+                            rebinding_screen.is_open = true;
                         }
                     }
                 );
@@ -157,11 +593,101 @@ fn paused_ui_system(
         );
 }
 
+fn rebinding_ui_system(
+    mut egui_ctx: ResMut<EguiContext>,
+    key_bindings: Res<KeyBindings>,
+    mut rebinding_screen: ResMut<RebindingScreen>,
+) {
+    if !rebinding_screen.is_open {
+        return;
+    }
+    egui::Area::new("rebinding-menu")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(
+            egui_ctx.ctx_mut(),
+            |ui| {
+                ui.with_layout(
+                    egui::Layout::top_down(egui::Align::Center), |ui| {
+                        ui.label(
+                            egui::RichText::new("Controls")
+                            .size(20.)
+                            .text_style(egui::TextStyle::Heading)
+                            .underline()
+                            .color(egui::Color32::BLACK)
+                        );
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        for player in Player::HEAD_TO_HEAD {
+                            ui.label(egui::RichText::new(player.to_string()).color(get_player_color32(player)));
+                            for action in Action::ALL {
+                                let player_action = PlayerAction(player, action);
+                                let key_text = key_bindings.get(player, action).map_or("Unbound".to_string(), |key| format!("{:?}", key));
+                                let button_text = if rebinding_screen.awaiting == Some(player_action) {
+                                    format!("{}: Press a key...", action)
+                                } else {
+                                    format!("{}: {}", action, key_text)
+                                };
+                                if ui.button(button_text).clicked() {
+                                    rebinding_screen.awaiting = Some(player_action);
+                                    rebinding_screen.message = None;
+                                }
+                            }
+                        }
+
+                        match &rebinding_screen.message {
+                            Some(message) => {
+                                ui.label(egui::RichText::new(message).color(egui::Color32::RED));
+                            },
+                            None => (),
+                        };
+
+                        ui.add_space(PAUSE_SCREEN_SPACING);
+                        if ui.button("Back").clicked() {
+                            *rebinding_screen = RebindingScreen::default();
+                        }
+                    }
+                );
+            }
+        );
+}
+
+fn rebinding_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut rebinding_screen: ResMut<RebindingScreen>,
+) {
+    let player_action = match rebinding_screen.awaiting {
+        Some(player_action) => player_action,
+        None => return,
+    };
+    let key = match keyboard_input.get_just_pressed().next() {
+        Some(key) => *key,
+        None => return,
+    };
+
+    rebinding_screen.awaiting = None;
+    match key_bindings.rebind(player_action, key) {
+        Ok(()) => {
+            rebinding_screen.message = None;
+            if let Err(error) = save_ron(&*key_bindings, get_user_bindings_path()) {
+                println!("Failed to save key bindings: {}", error);
+            }
+        },
+        Err(PlayerAction(conflicting_player, conflicting_action)) => {
+            rebinding_screen.message = Some(format!("{:?} is already bound to {} {}.", key, conflicting_player, conflicting_action));
+        }
+    }
+}
+
 fn paused_input_system(
     mut commands: Commands,
-    keyboard_input: Res<Input<KeyCode>>,
+    action_input: Res<Input<PlayerAction>>,
+    rebinding_screen: Res<RebindingScreen>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    if rebinding_screen.is_open {
+        return;
+    }
+    if Player::ALL.iter().any(|player| action_input.just_pressed(PlayerAction(*player, Action::Pause))) {
         commands.insert_resource(NextState(PongState::InGame));
     }
 }
@@ -208,7 +734,85 @@ fn scale_button(
     }
 }
 
-fn instantiate_projection_panel(egui_ctx: &mut EguiContext, image: Handle<Image>, id: &str, label: &str, align: egui::Align2) {
+// Highlights the button for whichever of a set of choices is current, and returns its value when clicked.
+fn selectable_button<T: PartialEq>(
+    ui: &mut egui::Ui,
+    text: &str,
+    value: T,
+    current_value: T,
+) -> Option<T> {
+    let color = if value == current_value {
+        egui::Color32::GREEN
+    } else {
+        egui::Color32::WHITE
+    };
+
+    if ui.button(egui::RichText::new(text).color(color)).clicked() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+fn get_control_hint(key_bindings: &KeyBindings, player: Player, control_scheme: ControlScheme) -> String {
+    match control_scheme {
+        ControlScheme::Keyboard => key_bindings.describe(player),
+        ControlScheme::Mouse => "Mouse moves x and y, scroll wheel moves z".to_string(),
+        ControlScheme::Gamepad => "Left stick moves x and y, triggers or right stick move z, Start pauses".to_string(),
+    }
+}
+
+pub fn get_player_color32(player: Player) -> egui::Color32 {
+    match player {
+        Player::Blue => egui::Color32::LIGHT_BLUE,
+        Player::Red => egui::Color32::LIGHT_RED,
+        Player::Green => egui::Color32::LIGHT_GREEN,
+        Player::Yellow => egui::Color32::LIGHT_YELLOW,
+    }
+}
+
+fn get_sets_text(match_score: &MatchScore, players: &[Player]) -> String {
+    let sets: Vec<String> = players.iter().map(|player| match_score.sets(*player).to_string()).collect();
+    format!("Sets: {}", sets.join(" - "))
+}
+
+// Each set's points, e.g. "Final Score: 11 - 7, 9 - 11, 11 - 5".
+fn get_final_score_text(match_score: &MatchScore, players: &[Player]) -> String {
+    let set_scores: Vec<String> = (0..match_score.finished_sets())
+        .map(|set_index| {
+            let points: Vec<String> = players.iter().map(|player| match_score.set_points(set_index, *player).to_string()).collect();
+            points.join(" - ")
+        })
+        .collect();
+    format!("Final Score: {}", set_scores.join(", "))
+}
+
+fn get_next_gamepad(connected_gamepads: &[Gamepad], current_gamepad: Option<Gamepad>) -> Option<Gamepad> {
+    // Cycle through the connected gamepads, with "no gamepad" after the last one.
+    match current_gamepad {
+        Some(current_gamepad) => {
+            let current_index = connected_gamepads.iter().position(|gamepad| *gamepad == current_gamepad)?;
+            connected_gamepads.get(current_index + 1).copied()
+        },
+        None => connected_gamepads.first().copied(),
+    }
+}
+
+pub fn toggle_button(
+    ui: &mut egui::Ui,
+    text: &str,
+    is_on: bool,
+) -> bool {
+    let (label, color) = if is_on {
+        (format!("{}: On", text), egui::Color32::GREEN)
+    } else {
+        (format!("{}: Off", text), egui::Color32::WHITE)
+    };
+
+    ui.button(egui::RichText::new(label).color(color)).clicked()
+}
+
+pub fn instantiate_projection_panel(egui_ctx: &mut EguiContext, image: Handle<Image>, id: &str, label: &str, align: egui::Align2) -> egui::Response {
     let texture = egui_ctx.add_image(image);
     egui::Area::new(id)
         .anchor(align, egui::Vec2::ZERO)
@@ -218,10 +822,39 @@ fn instantiate_projection_panel(egui_ctx: &mut EguiContext, image: Handle<Image>
                 ui.set_height(PROJECTION_AREA_HEIGHT);
                 ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                     ui.label(egui::RichText::new(label).color(egui::Color32::WHITE).underline());
-                    ui.image(texture, egui::vec2(PROJECTION_AREA_WIDTH, PROJECTION_AREA_HEIGHT));
-                });
+                    // Draggable so that the editor can move things along w.
+                    ui.add(egui::Image::new(texture, egui::vec2(PROJECTION_AREA_WIDTH, PROJECTION_AREA_HEIGHT)).sense(egui::Sense::drag()))
+                }).inner
             }
-        );
+        ).inner
+}
+
+// Covers the main view with the three projections, as large as will fit side by side.
+fn instantiate_projection_grid(egui_ctx: &mut EguiContext, projection_images: &ProjectionImages) {
+    let (xw_image, yw_image, zw_image) = projection_images.unpack();
+    let textures = [
+        (egui_ctx.add_image(xw_image), "X-W Projection"),
+        (egui_ctx.add_image(yw_image), "Y-W Projection"),
+        (egui_ctx.add_image(zw_image), "Z-W Projection"),
+    ];
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui::Color32::BLACK))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let available = ui.available_size();
+            let aspect_ratio = PROJECTION_AREA_WIDTH / PROJECTION_AREA_HEIGHT;
+            let width = (available.x / textures.len() as f32 - ui.spacing().item_spacing.x)
+                .min((available.y - PROJECTION_GRID_LABEL_HEIGHT) * aspect_ratio);
+            ui.add_space((available.y - width / aspect_ratio - PROJECTION_GRID_LABEL_HEIGHT).max(0.) / 2.);
+            ui.horizontal(|ui| {
+                for (texture, label) in textures {
+                    ui.vertical_centered(|ui| {
+                        ui.set_width(width);
+                        ui.label(egui::RichText::new(label).color(egui::Color32::WHITE).underline());
+                        ui.image(texture, egui::vec2(width, width / aspect_ratio));
+                    });
+                }
+            });
+        });
 }
 
 fn get_text_bundle(